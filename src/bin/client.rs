@@ -0,0 +1,363 @@
+use std::{collections::BTreeMap, env, io::{self, BufRead, ErrorKind}, net::TcpStream, sync::mpsc::{self, Receiver}, thread, time::Duration};
+
+use rmafia::{elections::tie::{Decided, Verdict}, games::registry::Scenarios, lobby::LobbyInfo, scenario::*, waiting::{OracleRef, PlayerId, WaitingBuilder, WaitingRoom}};
+use tungstenite::{client, Message, WebSocket};
+
+const POLL: Duration = Duration::from_millis(20);
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+const HELP: &str = "\
+commands:
+  nominate <id>|none       nominate a player during the debate, or abstain
+  hang <id>|none           vote to hang a defendant, or vote for no lynch
+  shoot <id>               fire the gun you were handed at night
+  draw <n>                 draw a lot when a tied vote goes to a lot draw
+  card <id> [<id>]|pass    play the last move card you drew after being hanged, or pass
+  use <power> <id> [<id>]  use a night power, e.g. `use Heal 3`
+  reveal <id> <power>      guess a power of a player as the wicked
+  sense <power> <id> <role> guess the role of a player with a custom power, e.g. `sense SixthSense 3 Doctor`
+  say <text>               talk to the table during the day
+  whisper <text>           talk to the mafia at night
+  last <text>              say your last words after you are out
+  seat <id>                hand the terminal to another seat (hot-seat only)
+  next                     move to the next phase (hot-seat only)
+  help                     show this message
+  quit                     leave the game";
+
+enum Command {
+    Pray(Pray),
+    Seat(PlayerId),
+    Next,
+    Help,
+    Quit,
+}
+
+fn parse_power(s: &str) -> Result<Power, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_owned()))
+        .ok()
+        .or_else(|| Tag::parse(s).map(Power::Custom))
+        .ok_or_else(|| format!("Unknown power: {}", s))
+}
+
+fn parse_id(s: &str) -> Result<PlayerId, String> {
+    s.parse().map_err(|_| format!("Invalid player number: {}", s))
+}
+
+fn pray(action: Act, query: Vec<PlayerId>, meta: Option<Meta>) -> Command {
+    Command::Pray(Pray { action, query, meta })
+}
+
+fn parse(line: &str) -> Result<Command, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["nominate", "none"] => Ok(pray(Act::Nominate, vec![], None)),
+        ["hang", "none"] | ["vote", "none"] => Ok(pray(Act::Vote, vec![], None)),
+        ["nominate", id] => Ok(pray(Act::Nominate, vec![parse_id(id)?], None)),
+        ["hang", id] | ["vote", id] => Ok(pray(Act::Vote, vec![parse_id(id)?], None)),
+        ["draw", lot] => {
+            let lot = lot.parse().map_err(|_| format!("Invalid lot: {}", lot))?;
+            Ok(pray(Act::Draw, vec![], Some(Meta::Lot(lot))))
+        },
+        ["shoot", id] => Ok(pray(Act::Shoot, vec![parse_id(id)?], None)),
+        ["reveal", id, power] => {
+            let guess = Meta::Has(parse_power(power)?);
+            Ok(pray(Act::Use(Power::Reveal), vec![parse_id(id)?], Some(guess)))
+        },
+        ["sense", power, id, role] => {
+            let guess = Meta::Role((*role).to_owned());
+            Ok(pray(Act::Use(parse_power(power)?), vec![parse_id(id)?], Some(guess)))
+        },
+        ["card", "pass"] => Ok(pray(Act::LastMove, vec![], None)),
+        ["card", ids @ ..] if !ids.is_empty() => {
+            let ids = ids.iter().map(|id| parse_id(id)).collect::<Result<Vec<_>, _>>()?;
+            Ok(pray(Act::LastMove, ids, None))
+        },
+        ["use", power, ids @ ..] if !ids.is_empty() => {
+            let ids = ids.iter().map(|id| parse_id(id)).collect::<Result<Vec<_>, _>>()?;
+            Ok(pray(Act::Use(parse_power(power)?), ids, None))
+        },
+        ["say", ..] | ["whisper", ..] | ["last", ..] if words.len() > 1 => {
+            let channel = match words[0] {
+                "say" => Channel::Public,
+                "whisper" => Channel::Mafia,
+                _ => Channel::LastWords,
+            };
+            Ok(pray(Act::Talk, vec![], Some(Meta::Say(channel, words[1..].join(" ")))))
+        },
+        ["seat", id] => Ok(Command::Seat(parse_id(id)?)),
+        ["next"] => Ok(Command::Next),
+        ["help"] => Ok(Command::Help),
+        ["quit"] | ["exit"] => Ok(Command::Quit),
+        _ => Err(format!("Unknown command: {} (try `help`)", line.trim())),
+    }
+}
+
+fn describe(d: &Declaration) -> Option<String> {
+    match d {
+        Declaration::Out(id) => Some(format!("player {} is out", id)),
+        Declaration::StateChanged(CityState::Done(State::MafiaWon)) => Some("mafia won".to_owned()),
+        Declaration::StateChanged(CityState::Done(State::CityWon)) => Some("city won".to_owned()),
+        Declaration::StateChanged(CityState::Done(State::Draw)) => Some("nobody won".to_owned()),
+        Declaration::StateChanged(CityState::Done(State::Won(winners))) => {
+            let names: Vec<String> = winners.iter().map(|f| format!("{:?}", f)).collect();
+            Some(format!("{} won", names.join(" and ")))
+        },
+        Declaration::StateChanged(state) => Some(format!("it is {:?} now", state)),
+        Declaration::FakeGun(id) => Some(format!("an empty gun was fired at {}", id)),
+        Declaration::Seats(ids) => Some(format!("players at the table: {:?}", ids)),
+        Declaration::OnTrial(ids) => Some(format!("on trial: {:?}", ids)),
+        Declaration::LotDraw(ids) => Some(format!("tied players {:?} draw lots, use `draw <n>`", ids)),
+        Declaration::Verdict(v) => Some(describe_verdict(v)),
+        Declaration::CardDrawn(id, card) => Some(format!("player {} drew the {:?} card, use `card <id>`", id, card)),
+        Declaration::CardPlayed(id, card, on) if on.is_empty() => Some(format!("player {} passed the {:?} card", id, card)),
+        Declaration::CardPlayed(id, card, on) => Some(format!("player {} played {:?} on {:?}", id, card, on)),
+        Declaration::Back(id) => Some(format!("player {} is back in the game", id)),
+        Declaration::Exposed(id) => Some(format!("player {} was exposed as mafia", id)),
+        Declaration::Seed(_) => None,
+    }
+}
+
+fn is_public(msg: &HolyMessage) -> bool {
+    match msg {
+        HolyMessage::Said(_, channel, _) => *channel != Channel::Mafia,
+        HolyMessage::Declared(_) | HolyMessage::Nominated { .. } | HolyMessage::Voted { .. }
+            | HolyMessage::Abstained { .. } | HolyMessage::Drew { .. } | HolyMessage::Tally(_) => true,
+        _ => false,
+    }
+}
+
+fn describe_verdict(v: &Verdict) -> String {
+    let by = match v.decided {
+        Decided::Votes => "by votes".to_owned(),
+        Decided::Policy(policy) => format!("by {:?} between {:?}", policy, v.tied),
+    };
+    match v.hanged {
+        Some(id) => format!("player {} is hanged {}", id, by),
+        None => format!("nobody is hanged {}", by),
+    }
+}
+
+fn describe_tally(tally: &Tally) -> String {
+    let votes = tally.votes.iter()
+        .map(|(on, from)| format!("{}: {} {:?}", on, from.len(), from))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let verb = if tally.action == Act::Nominate { "on trial" } else { "to hang" };
+    format!("{:?} tally (needs {}): {} | abstained {:?} | {}: {:?}", tally.action, tally.threshold, votes, tally.abstained, verb, tally.crossed)
+}
+
+struct Seat {
+    id: PlayerId,
+    powers: Vec<Power>,
+    state: CityState,
+    day: Day,
+    inbox: Vec<String>,
+}
+
+impl Seat {
+    fn new(id: PlayerId) -> Self {
+        Self { id, powers: Vec::new(), state: CityState::Debate, day: 0, inbox: Vec::new() }
+    }
+
+    fn status(&self) -> String {
+        format!("[seat {} | day {} | {:?} | powers {:?}]", self.id, self.day, self.state, self.powers)
+    }
+
+    fn on(&mut self, msg: HolyMessage) -> Option<String> {
+        match msg {
+            HolyMessage::Seated(id) => {
+                self.id = id;
+                Some(format!("you sit at seat {}", id))
+            },
+            HolyMessage::Session(token) => Some(format!("to reconnect later use /resume/{}", token)),
+            HolyMessage::Assigned(powers) => {
+                self.powers = powers;
+                Some(format!("your powers: {:?}", self.powers))
+            },
+            HolyMessage::YouHaveGun => Some("you were handed a gun".to_owned()),
+            HolyMessage::YouAreBoss => Some("you are the boss of the mafia now".to_owned()),
+            HolyMessage::IsMafia(id, yes) => Some(format!("player {} is {}mafia", id, if yes { "" } else { "not " })),
+            HolyMessage::MafiaAmong(ids, n) => Some(format!("{} of players {:?} are mafia", n, ids)),
+            HolyMessage::Said(id, Channel::Public, text) => Some(format!("{} says: {}", id, text)),
+            HolyMessage::Said(id, Channel::Mafia, text) => Some(format!("{} whispers: {}", id, text)),
+            HolyMessage::Said(id, Channel::LastWords, text) => Some(format!("last words of {}: {}", id, text)),
+            HolyMessage::Nominated { from, on } => Some(format!("{} nominated {}", from, on)),
+            HolyMessage::Voted { from, on } => Some(format!("{} voted to hang {}", from, on)),
+            HolyMessage::Abstained { from, action } => Some(format!("{} abstained from {:?}", from, action)),
+            HolyMessage::Drew { from, lot } => Some(format!("{} drew lot {}", from, lot)),
+            HolyMessage::Tally(tally) => Some(describe_tally(&tally)),
+            HolyMessage::Rejected { reason } => Some(format!("rejected: {}", reason)),
+            HolyMessage::Declared(d) => {
+                if let Declaration::StateChanged(state) = &d {
+                    if self.state == CityState::Night && *state == CityState::Debate {
+                        self.day += 1;
+                    }
+                    self.state = state.clone();
+                }
+                describe(&d)
+            },
+        }
+    }
+}
+
+fn stdin_lines() -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break
+            }
+        }
+    });
+    rx
+}
+
+fn play_online(addr: &str, path: &str) -> Result<(), String> {
+    let stream = TcpStream::connect(addr).map_err(|e| format!("Unable to connect: {}", e))?;
+    let (mut ws, _) = client(format!("ws://{}{}", addr, path), stream).map_err(|e| e.to_string())?;
+    ws.get_ref().set_read_timeout(Some(POLL)).map_err(|e| e.to_string())?;
+
+    let lines = stdin_lines();
+    let mut seat = Seat::new(0);
+    println!("{}", HELP);
+    loop {
+        if let Some(msg) = read_frame(&mut ws)? {
+            if let Some(text) = seat.on(msg) {
+                println!("{} {}", seat.status(), text);
+            }
+            if let CityState::Done(_) = seat.state {
+                return Ok(())
+            }
+        }
+
+        if let Ok(line) = lines.try_recv() {
+            match parse(&line) {
+                Ok(Command::Pray(p)) => {
+                    let text = serde_json::to_string(&p).map_err(|e| e.to_string())?;
+                    ws.send(Message::Text(text)).map_err(|e| e.to_string())?;
+                },
+                Ok(Command::Help) => println!("{}", HELP),
+                Ok(Command::Quit) => return ws.close(None).map_err(|e| e.to_string()),
+                Ok(_) => println!("only available in hot-seat mode"),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+}
+
+fn read_frame(ws: &mut WebSocket<TcpStream>) -> Result<Option<HolyMessage>, String> {
+    match ws.read() {
+        Ok(Message::Text(text)) => {
+            if let Ok(info) = serde_json::from_str::<LobbyInfo>(&text) {
+                println!("lobby {} ({}): {}/{} players", info.code, info.scenario, info.joined, info.limit);
+            } else if let Ok(list) = serde_json::from_str::<Vec<LobbyInfo>>(&text) {
+                for info in list {
+                    println!("lobby {} ({}): {}/{} players", info.code, info.scenario, info.joined, info.limit);
+                }
+            }
+            Ok(serde_json::from_str(&text).ok())
+        },
+        Ok(Message::Close(_)) => Err("Server closed the connection".to_owned()),
+        Ok(_) => Ok(None),
+        Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn play_hot_seat(scenario: &str, players: usize) -> Result<(), String> {
+    let scenarios = Scenarios::default();
+    scenarios.check(scenario, players)?;
+    let mut waiting = WaitingBuilder::new(players);
+    let mut seats: BTreeMap<PlayerId, (Seat, OracleRef)> = BTreeMap::new();
+    while let Some((id, _, oracle)) = waiting.reserve() {
+        seats.insert(id, (Seat::new(id), oracle));
+    }
+    let room = waiting.get().ok_or_else(|| "Unable to fill the table".to_owned())?;
+    let mut game = scenarios.build(scenario, room, &serde_json::Value::Null, rand::random())?;
+
+    let lines = stdin_lines();
+    let mut current = *seats.keys().next().ok_or_else(|| "No seats".to_owned())?;
+    let mut told = 0;
+    println!("{}", HELP);
+    hand_over(&lines, &seats[&current].0)?;
+    loop {
+        for (id, (seat, oracle)) in seats.iter_mut() {
+            while let Some(msg) = oracle.read() {
+                let public = is_public(&msg);
+                let declared = matches!(msg, HolyMessage::Declared(_));
+                match seat.on(msg) {
+                    Some(text) if !public => seat.inbox.push(text),
+                    Some(text) if !declared && *id == current => println!("{}", text),
+                    _ => {},
+                }
+            }
+        }
+        if let Some((seat, _)) = seats.get_mut(&current) {
+            for text in seat.inbox.drain(..) {
+                println!("(private) {}", text);
+            }
+        }
+
+        for (day, _, d) in game.events().iter().skip(told) {
+            if let Some(text) = describe(d) {
+                println!("day {} | {}", day, text);
+            }
+        }
+        told = game.events().len();
+
+        if let CityState::Done(_) = game.state() {
+            return Ok(())
+        }
+
+        match lines.recv_timeout(POLL) {
+            Ok(line) => match parse(&line) {
+                Ok(Command::Pray(p)) => match seats.get_mut(&current) {
+                    Some((_, oracle)) => {
+                        oracle.tell(p);
+                    },
+                    None => println!("no seat {}", current),
+                },
+                Ok(Command::Seat(id)) if seats.contains_key(&id) => {
+                    current = id;
+                    hand_over(&lines, &seats[&id].0)?;
+                },
+                Ok(Command::Seat(id)) => println!("no seat {}", id),
+                Ok(Command::Next) => {
+                    game.process();
+                    game.next();
+                },
+                Ok(Command::Help) => println!("{}", HELP),
+                Ok(Command::Quit) => return Ok(()),
+                Err(e) => println!("{}", e),
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => {},
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        if game.process() {
+            game.next();
+        }
+    }
+}
+
+fn hand_over(lines: &Receiver<String>, seat: &Seat) -> Result<(), String> {
+    print!("{}", CLEAR);
+    println!("hand the terminal to seat {} and press enter", seat.id);
+    lines.recv().map_err(|_| "Input closed".to_owned())?;
+    print!("{}", CLEAR);
+    println!("{}", seat.status());
+    Ok(())
+}
+
+fn main() -> Result<(), String> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["--hot-seat"] => play_hot_seat(Scenarios::DEFAULT, 10),
+        ["--hot-seat", n] => play_hot_seat(Scenarios::DEFAULT, n.parse().map_err(|_| format!("Invalid number of players: {}", n))?),
+        ["--hot-seat", n, scenario] => play_hot_seat(scenario, n.parse().map_err(|_| format!("Invalid number of players: {}", n))?),
+        [addr, path] => play_online(addr, path),
+        [addr] => play_online(addr, "/"),
+        [] => play_online("127.0.0.1:9001", "/"),
+        _ => Err("usage: client [<host:port> [/lobbies | /new/[<scenario>/]<players>[?<options>] | /join/<code>] | --hot-seat [<players> [<scenario>]]]".to_owned()),
+    }
+}
@@ -0,0 +1,49 @@
+use std::{env, fs};
+
+use rmafia::{games::classic::{composition::Composition, roles}, simulation::{Bots, Simulation}};
+
+const USAGE: &str = "usage: simulate [--games <n>] [--players <n>] [--seed <n>] [--bots random|heuristic|mixed] [--composition <file.json>]";
+
+fn number<T: std::str::FromStr>(value: Option<String>, name: &str) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", name))?;
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+fn main() -> Result<(), String> {
+    let mut games = 1000;
+    let mut players = 10;
+    let mut seed = 0;
+    let mut bots = Bots::Mixed;
+    let mut composition = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => games = number(args.next(), "--games")?,
+            "--players" => players = number(args.next(), "--players")?,
+            "--seed" => seed = number(args.next(), "--seed")?,
+            "--bots" => bots = match args.next().as_deref() {
+                Some("random") => Bots::Random,
+                Some("heuristic") => Bots::Heuristic,
+                Some("mixed") => Bots::Mixed,
+                _ => return Err(USAGE.to_owned()),
+            },
+            "--composition" => {
+                let path = args.next().ok_or_else(|| USAGE.to_owned())?;
+                let json = fs::read_to_string(&path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+                composition = Some(Composition::from_json(&json)?);
+            },
+            _ => return Err(USAGE.to_owned()),
+        }
+    }
+
+    let composition = match composition {
+        Some(c) => c,
+        None => Composition::recommended(players)?,
+    }.resolve(&roles::registry())?;
+    composition.validate(composition.players())?;
+
+    let report = Simulation::new(composition, bots).run(games, seed)?;
+    print!("{}", report);
+    Ok(())
+}
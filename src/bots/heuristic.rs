@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use rand::SeedableRng;
+
+use crate::scenario::*;
+
+use super::*;
+
+pub struct HeuristicBot {
+    rng: GameRng,
+    enquired: BTreeMap<PlayerId, bool>,
+}
+
+impl HeuristicBot {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: GameRng::seed_from_u64(seed), enquired: BTreeMap::new() }
+    }
+
+    fn known_mafia<'a>(&'a self, memory: &'a Memory) -> impl Iterator<Item = PlayerId> + 'a {
+        memory.others().filter(move |id| self.enquired.get(id) == Some(&true))
+    }
+
+    fn suspect(&mut self, memory: &Memory, among: &[PlayerId]) -> Option<PlayerId> {
+        if memory.has(&Power::Mafia) {
+            return pick(&mut self.rng, among.iter().cloned())
+        }
+
+        let known = self.known_mafia(memory).find(|id| among.contains(id));
+        known.or_else(|| {
+            let unknown = among.iter().cloned()
+                .filter(|id| self.enquired.get(id) != Some(&false))
+                .collect::<Vec<_>>();
+            pick(&mut self.rng, unknown.into_iter())
+        })
+    }
+
+    fn night(&mut self, memory: &Memory) -> Vec<Pray> {
+        let others = memory.others().collect::<Vec<_>>();
+        let mut prays = Vec::new();
+        for power in memory.actions() {
+            let target = match power {
+                Power::Heal | Power::Guard => Some(memory.id),
+                Power::Enquery => {
+                    let unknown = others.iter().cloned()
+                        .filter(|id| !self.enquired.contains_key(id))
+                        .collect::<Vec<_>>();
+                    pick(&mut self.rng, unknown.into_iter())
+                },
+                Power::HandGun | Power::HandFakeGun => {
+                    let trusted = others.iter().cloned()
+                        .filter(|id| self.enquired.get(id) != Some(&true))
+                        .collect::<Vec<_>>();
+                    pick(&mut self.rng, trusted.into_iter())
+                },
+                Power::Reveal => None,
+                _ => self.suspect(memory, &others),
+            };
+            prays.extend(target.map(|id| pray(Act::Use(*power), id)));
+        }
+        prays
+    }
+}
+
+impl Bot for HeuristicBot {
+    fn learn(&mut self, msg: &HolyMessage, _memory: &Memory) {
+        if let HolyMessage::IsMafia(id, is_mafia) = msg {
+            self.enquired.insert(*id, *is_mafia);
+        }
+    }
+
+    fn last_move(&mut self, card: Card, memory: &Memory) -> Option<Pray> {
+        super::last_move(&mut self.rng, card, memory)
+    }
+
+    fn act(&mut self, memory: &Memory) -> Vec<Pray> {
+        match memory.state {
+            CityState::Debate => {
+                let mut prays = Vec::new();
+                if memory.armed && !memory.has(&Power::Mafia) {
+                    prays.extend(self.known_mafia(memory).next().map(|id| pray(Act::Shoot, id)));
+                }
+                let others = memory.others().collect::<Vec<_>>();
+                prays.extend(self.suspect(memory, &others).map(|id| pray(Act::Nominate, id)));
+                prays
+            },
+            CityState::Hang if !memory.lots.is_empty() => draw(&mut self.rng, memory).into_iter().collect(),
+            CityState::Hang => {
+                let defendants = memory.on_trial.iter().cloned()
+                    .filter(|id| *id != memory.id)
+                    .collect::<Vec<_>>();
+                self.suspect(memory, &defendants).map(|id| pray(Act::Vote, id)).into_iter().collect()
+            },
+            CityState::Night => self.night(memory),
+            _ => vec![]
+        }
+    }
+}
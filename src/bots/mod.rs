@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+
+use rand::{seq::IteratorRandom, Rng};
+
+use crate::{cards::Card, scenario::*, waiting::{OracleRef, PlayerId}};
+
+pub mod heuristic;
+pub mod random;
+
+pub struct Memory {
+    pub id: PlayerId,
+    pub powers: Vec<Power>,
+    pub abilities: BTreeSet<Power>,
+    pub alive: BTreeSet<PlayerId>,
+    pub on_trial: BTreeSet<PlayerId>,
+    pub state: CityState,
+    pub armed: bool,
+    pub lots: Vec<PlayerId>,
+}
+
+impl Memory {
+    pub fn new(id: PlayerId) -> Self {
+        Self {
+            id,
+            powers: Vec::new(),
+            abilities: BTreeSet::new(),
+            alive: BTreeSet::new(),
+            on_trial: BTreeSet::new(),
+            state: CityState::Debate,
+            armed: false,
+            lots: Vec::new(),
+        }
+    }
+
+    fn learn(&mut self, msg: &HolyMessage) -> bool {
+        match msg {
+            HolyMessage::Seated(id) => self.id = *id,
+            HolyMessage::Assigned(powers) => self.powers = powers.clone(),
+            HolyMessage::YouHaveGun => self.armed = true,
+            HolyMessage::Declared(Declaration::Seats(ids)) => self.alive = ids.iter().cloned().collect(),
+            HolyMessage::Declared(Declaration::Out(id)) => {
+                self.alive.remove(id);
+            },
+            HolyMessage::Declared(Declaration::Back(id)) => {
+                self.alive.insert(*id);
+            },
+            HolyMessage::Declared(Declaration::OnTrial(ids)) => self.on_trial = ids.iter().cloned().collect(),
+            HolyMessage::Declared(Declaration::LotDraw(ids)) => self.lots = ids.clone(),
+            HolyMessage::Declared(Declaration::StateChanged(state)) => {
+                self.state = state.clone();
+                if *state == CityState::Night {
+                    self.armed = false;
+                    self.on_trial.clear();
+                    self.lots.clear();
+                }
+                return true
+            },
+            _ => {}
+        }
+        false
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive.contains(&self.id)
+    }
+
+    pub fn has(&self, power: &Power) -> bool {
+        self.powers.contains(power)
+    }
+
+    /// The powers this player can use at night.
+    pub fn actions(&self) -> impl Iterator<Item = &Power> + '_ {
+        self.powers.iter().filter(move |p| self.abilities.contains(p))
+    }
+
+    pub fn others(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        self.alive.iter().cloned().filter(move |id| *id != self.id)
+    }
+}
+
+pub trait Bot: Send {
+    fn learn(&mut self, _msg: &HolyMessage, _memory: &Memory) {}
+    fn act(&mut self, memory: &Memory) -> Vec<Pray>;
+
+    fn last_move(&mut self, _card: Card, _memory: &Memory) -> Option<Pray> {
+        None
+    }
+}
+
+pub fn pray(action: Act, on: PlayerId) -> Pray {
+    Pray { action, query: vec![on], meta: None }
+}
+
+pub fn draw(rng: &mut GameRng, memory: &Memory) -> Option<Pray> {
+    if !memory.lots.contains(&memory.id) {
+        return None
+    }
+    let lot = rng.gen_range(1..=memory.lots.len());
+    Some(Pray { action: Act::Draw, query: vec![], meta: Some(Meta::Lot(lot)) })
+}
+
+pub fn last_move(rng: &mut GameRng, card: Card, memory: &Memory) -> Option<Pray> {
+    let query = memory.alive.iter().cloned().choose_multiple(rng, *card.targets().start());
+    if query.is_empty() {
+        return None
+    }
+    Some(Pray { action: Act::LastMove, query, meta: None })
+}
+
+pub fn pick(rng: &mut GameRng, ids: impl Iterator<Item = PlayerId>) -> Option<PlayerId> {
+    ids.choose(rng)
+}
+
+pub struct BotSeat {
+    oracle: OracleRef,
+    memory: Memory,
+    bot: Box<dyn Bot>,
+}
+
+impl BotSeat {
+    pub fn new(id: PlayerId, oracle: OracleRef, bot: Box<dyn Bot>, abilities: BTreeSet<Power>) -> Self {
+        let memory = Memory { abilities, ..Memory::new(id) };
+        Self { oracle, memory, bot }
+    }
+
+    pub fn id(&self) -> PlayerId {
+        self.memory.id
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn tick(&mut self) {
+        while let Some(msg) = self.oracle.read() {
+            let changed = self.memory.learn(&msg);
+            self.bot.learn(&msg, &self.memory);
+            match msg {
+                HolyMessage::Declared(Declaration::CardDrawn(id, card)) if id == self.memory.id => {
+                    let pass = Pray { action: Act::LastMove, query: vec![], meta: None };
+                    let p = self.bot.last_move(card, &self.memory).unwrap_or(pass);
+                    self.oracle.tell(p);
+                },
+                _ => {}
+            }
+            if changed && self.memory.is_alive() {
+                for p in self.bot.act(&self.memory) {
+                    self.oracle.tell(p);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use rand::{Rng, SeedableRng};
+
+use crate::scenario::*;
+
+use super::*;
+
+const GUESSES: [Power; 4] = [Power::Heal, Power::DayShield, Power::Enquery, Power::ShotOnKill];
+
+pub struct RandomBot {
+    rng: GameRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: GameRng::seed_from_u64(seed) }
+    }
+
+    fn night(&mut self, memory: &Memory) -> Vec<Pray> {
+        let mut prays = Vec::new();
+        for power in memory.actions() {
+            let target = match power {
+                Power::Heal | Power::Guard => pick(&mut self.rng, memory.alive.iter().cloned()),
+                _ => pick(&mut self.rng, memory.others()),
+            };
+            let target = match target {
+                Some(id) => id,
+                None => continue
+            };
+
+            match power {
+                Power::Reveal if self.rng.gen_bool(0.2) => {
+                    let guess = GUESSES[self.rng.gen_range(0..GUESSES.len())];
+                    prays.push(Pray { action: Act::Use(*power), query: vec![target], meta: Some(Meta::Has(guess)) });
+                },
+                Power::Reveal => {},
+                _ => prays.push(pray(Act::Use(*power), target)),
+            }
+        }
+        prays
+    }
+}
+
+impl Bot for RandomBot {
+    fn last_move(&mut self, card: Card, memory: &Memory) -> Option<Pray> {
+        super::last_move(&mut self.rng, card, memory)
+    }
+
+    fn act(&mut self, memory: &Memory) -> Vec<Pray> {
+        match memory.state {
+            CityState::Debate => {
+                let mut prays = Vec::new();
+                if memory.armed {
+                    prays.extend(pick(&mut self.rng, memory.others()).map(|id| pray(Act::Shoot, id)));
+                }
+                prays.extend(pick(&mut self.rng, memory.others()).map(|id| pray(Act::Nominate, id)));
+                prays
+            },
+            CityState::Hang if !memory.lots.is_empty() => draw(&mut self.rng, memory).into_iter().collect(),
+            CityState::Hang => {
+                let defendants = memory.on_trial.iter().cloned().filter(|id| *id != memory.id);
+                pick(&mut self.rng, defendants).map(|id| pray(Act::Vote, id)).into_iter().collect()
+            },
+            CityState::Night => self.night(memory),
+            _ => vec![]
+        }
+    }
+}
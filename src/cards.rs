@@ -0,0 +1,59 @@
+use std::ops::RangeInclusive;
+
+use rand::prelude::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{scenario::*, waiting::PlayerId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Card {
+    SilenceOfTheLambs,
+    FaceOff,
+    BeautifulMind,
+    Handcuffs,
+}
+
+impl Card {
+    pub const ALL: [Card; 4] = [Card::SilenceOfTheLambs, Card::FaceOff, Card::BeautifulMind, Card::Handcuffs];
+
+    pub fn targets(&self) -> RangeInclusive<usize> {
+        match self {
+            Card::SilenceOfTheLambs => 1..=2,
+            Card::FaceOff | Card::BeautifulMind | Card::Handcuffs => 1..=1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Deck {
+    cards: Vec<Card>,
+}
+
+impl Deck {
+    pub fn new(rng: &mut GameRng) -> Self {
+        let mut cards = Card::ALL.to_vec();
+        cards.shuffle(rng);
+        Self { cards }
+    }
+
+    pub fn draw(&mut self) -> Option<Card> {
+        self.cards.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hand {
+    pub holder: PlayerId,
+    pub card: Card,
+    #[serde(default)]
+    pub role: Option<String>,
+    pub powers: Vec<Power>,
+}
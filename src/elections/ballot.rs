@@ -1,32 +1,56 @@
 use std::collections::{BTreeSet,BTreeMap};
+use serde::{Deserialize, Serialize};
 use crate::{scenario::*, waiting::PlayerId};
 
+use super::voting::Threshold;
+
+#[derive(Serialize, Deserialize)]
 pub struct DeathVote {
-    total: u8,
+    threshold: Threshold,
     list: BTreeSet<PlayerId>,
-    votes: BTreeMap<PlayerId, BTreeSet<PlayerId>>
+    votes: BTreeMap<PlayerId, BTreeSet<PlayerId>>,
+    abstained: BTreeSet<PlayerId>,
 }
 
 impl DeathVote {
-    fn new(nomonies: BTreeSet<PlayerId>, total: u8) -> Result<DeathVote, Error> {
-        assert!(total >= 3);
-        if nomonies.len() == 0 {
-            return Err("No nominies found".to_owned())
+    fn new(nomonies: BTreeSet<PlayerId>, threshold: Threshold) -> Result<DeathVote, Error> {
+        if nomonies.is_empty() {
+            return Err(Rule::NoNominees.into())
         }
         let mut list = BTreeMap::new();
         for p in nomonies.iter() {
-            list.insert(p.clone(), BTreeSet::new());
+            list.insert(*p, BTreeSet::new());
         }
         
         let vote = Self {
-            total: total,
+            threshold,
             list: nomonies,
-            votes: list
+            votes: list,
+            abstained: BTreeSet::new(),
         };
         Ok(vote)
     }
 }
 
+impl DeathVote {
+    pub fn revote(&self, tied: Vec<PlayerId>) -> Result<DeathVote, Error> {
+        DeathVote::new(tied.into_iter().collect(), self.threshold)
+    }
+
+    fn cast(&self) -> usize {
+        let voters = self.votes.values().flatten().collect::<BTreeSet<_>>();
+        voters.len() + self.abstained.len()
+    }
+
+    fn required(&self, living: usize) -> usize {
+        self.threshold.required(living, self.cast())
+    }
+
+    fn crossed(&self, living: usize) -> Vec<PlayerId> {
+        crossed(&self.votes, self.threshold, self.required(living))
+    }
+}
+
 impl DeathBallot for DeathVote {
     fn list(&self) -> &BTreeSet<PlayerId> {
         &self.list
@@ -39,42 +63,87 @@ impl DeathBallot for DeathVote {
         if self.list.len() == 2 && self.list.contains(&from) {
             return false;
         }
-        if let Some(votes) = self.votes.get_mut(&on) {
-            votes.insert(from)
-        } else {
-            false
+        for (defendant, votes) in self.votes.iter_mut() {
+            if *defendant != on {
+                votes.remove(&from);
+            }
         }
+        let voted = self.votes.get_mut(&on).is_some_and(|votes| votes.insert(from));
+        if voted {
+            self.abstained.remove(&from);
+        }
+        voted
     }
 
-    fn dead(&self) -> Option<PlayerId> {
-        let half = (self.total / 2) as usize;
-        let mut ns =  self.votes.iter().filter_map(|(p, v)| {
-            if v.len() >= half {
-                Some((p, v.len()))
-            } else {
-                None
-            }
-        }).collect::<Vec<_>>();
-        ns.sort_by(|(_, v1), (_, v2)| {
-            v1.cmp(&v2)
-        });
-        ns.last().map(|&(p, _)| p.clone())
+    fn abstain(&mut self, from: PlayerId) -> bool {
+        if self.list.len() == 2 && self.list.contains(&from) {
+            return false;
+        }
+        for votes in self.votes.values_mut() {
+            votes.remove(&from);
+        }
+        self.abstained.insert(from)
+    }
+
+    fn dead(&self, living: usize) -> Option<PlayerId> {
+        match self.leaders(living).as_slice() {
+            [one] => Some(*one),
+            _ => None
+        }
+    }
+
+    fn leaders(&self, living: usize) -> Vec<PlayerId> {
+        let crossed = self.crossed(living);
+        let most = crossed.iter().map(|p| self.votes[p].len()).max();
+        if most.is_some_and(|most| self.abstained.len() >= most) {
+            return vec![]
+        }
+        crossed.into_iter()
+            .filter(|p| Some(self.votes[p].len()) == most)
+            .collect()
+    }
+
+    fn tally(&self, living: usize) -> Tally {
+        Tally {
+            action: Act::Vote,
+            votes: self.votes.clone(),
+            abstained: self.abstained.clone(),
+            threshold: self.required(living),
+            crossed: self.leaders(living),
+        }
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Ballots {
-    size: u8,
-    votes: BTreeMap<PlayerId, BTreeSet<PlayerId>>
+    threshold: Threshold,
+    hang: Threshold,
+    votes: BTreeMap<PlayerId, BTreeSet<PlayerId>>,
+    abstained: BTreeSet<PlayerId>,
 }
 
 impl Ballots {
-    pub fn new(total: usize) -> Ballots {
-        assert!(total >= 3);
+    pub fn new(threshold: Threshold, hang: Threshold) -> Ballots {
         Self {
-            size: total as u8,
-            votes: BTreeMap::new()
+            threshold,
+            hang,
+            votes: BTreeMap::new(),
+            abstained: BTreeSet::new(),
         }
-    }    
+    }
+
+    fn cast(&self) -> usize {
+        let voters = self.votes.values().flatten().collect::<BTreeSet<_>>();
+        voters.len() + self.abstained.len()
+    }
+
+    fn required(&self, living: usize) -> usize {
+        self.threshold.required(living, self.cast())
+    }
+
+    fn crossed(&self, living: usize) -> Vec<PlayerId> {
+        crossed(&self.votes, self.threshold, self.required(living))
+    }
 }
 
 impl Defendence for Ballots {
@@ -85,6 +154,7 @@ impl Defendence for Ballots {
             return false
         }
 
+        self.abstained.remove(&from);
         if let Some(votes) = self.votes.get_mut(&on) {
             votes.insert(from)
         } else {
@@ -94,20 +164,147 @@ impl Defendence for Ballots {
         }
     }
 
-    fn result(&self) -> Option<Self::Ballot> {
-        let half = self.size / 2;
-        let nomonies = self.votes.iter().filter_map(|(player, votes)| {
-            if votes.len() >= (half as usize) {
-                Some(player.clone())
-            } else {
-                None                
-            }
-        }).collect::<BTreeSet<_>>();
-        
-        if nomonies.len() != 0 {
-            Some(DeathVote::new(nomonies, self.size).unwrap())
-        } else {
-            None
+    fn abstain(&mut self, from: PlayerId) -> bool {
+        for votes in self.votes.values_mut() {
+            votes.remove(&from);
+        }
+        self.votes.retain(|_, votes| !votes.is_empty());
+        self.abstained.insert(from)
+    }
+
+    fn result(&self, living: usize) -> Result<Option<Self::Ballot>, Error> {
+        if living < 3 {
+            return Err(Config::TooFewPlayers(living).into())
+        }
+        let nomonies = self.crossed(living).into_iter().collect::<BTreeSet<_>>();
+        if nomonies.is_empty() {
+            return Ok(None)
+        }
+        DeathVote::new(nomonies, self.hang).map(Some)
+    }
+
+    fn tally(&self, living: usize) -> Tally {
+        Tally {
+            action: Act::Nominate,
+            votes: self.votes.clone(),
+            abstained: self.abstained.clone(),
+            threshold: self.required(living),
+            crossed: self.crossed(living),
+        }
+    }
+}
+
+fn crossed(votes: &BTreeMap<PlayerId, BTreeSet<PlayerId>>, threshold: Threshold, required: usize) -> Vec<PlayerId> {
+    let crossed = votes.iter()
+        .filter(|(_, v)| v.len() >= required)
+        .map(|(p, v)| (*p, v.len()))
+        .collect::<Vec<_>>();
+
+    let most = crossed.iter().map(|(_, n)| *n).max();
+    crossed.into_iter()
+        .filter(|(_, n)| !threshold.plurality() || Some(*n) == most)
+        .map(|(p, _)| p)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballots(threshold: Threshold, nominations: &[(PlayerId, PlayerId)], abstained: &[PlayerId]) -> Ballots {
+        let mut ballots = Ballots::new(threshold, Threshold::Half);
+        for (from, on) in nominations {
+            ballots.nominate(*from, *on);
+        }
+        for from in abstained {
+            ballots.abstain(*from);
         }
+        ballots
     }
-}
\ No newline at end of file
+
+    fn defendants(ballots: &Ballots, living: usize) -> Vec<PlayerId> {
+        ballots.result(living).unwrap()
+            .map(|vote| vote.list().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn abstentions_count_towards_a_simple_majority() {
+        let nominations = [(2, 1), (3, 1), (4, 1)];
+        assert_eq!(defendants(&ballots(Threshold::SimpleMajority, &nominations, &[5, 6]), 10), vec![1]);
+        assert!(defendants(&ballots(Threshold::SimpleMajority, &nominations, &[5, 6, 7]), 10).is_empty());
+    }
+
+    #[test]
+    fn abstaining_withdraws_nominations() {
+        let mut ballots = ballots(Threshold::Fixed(2), &[(2, 1), (3, 1)], &[3]);
+        assert!(defendants(&ballots, 10).is_empty());
+        ballots.nominate(3, 1);
+        assert_eq!(defendants(&ballots, 10), vec![1]);
+        assert!(ballots.tally(10).abstained.is_empty());
+    }
+
+    #[test]
+    fn fixed_and_living_thresholds() {
+        let nominations = [(2, 1), (3, 1), (4, 1), (5, 1), (6, 7), (8, 7)];
+        assert_eq!(defendants(&ballots(Threshold::Fixed(2), &nominations, &[]), 10), vec![1, 7]);
+        assert_eq!(defendants(&ballots(Threshold::Fixed(3), &nominations, &[]), 10), vec![1]);
+        assert_eq!(defendants(&ballots(Threshold::Half, &nominations, &[]), 8), vec![1]);
+        assert!(defendants(&ballots(Threshold::StrictMajority, &nominations, &[]), 8).is_empty());
+    }
+
+    #[test]
+    fn plurality_keeps_only_the_most_nominated() {
+        let nominations = [(2, 1), (3, 1), (4, 7)];
+        assert_eq!(defendants(&ballots(Threshold::Plurality, &nominations, &[]), 10), vec![1]);
+    }
+
+    #[test]
+    fn too_few_living_players_cannot_vote() {
+        assert_eq!(ballots(Threshold::Half, &[(2, 1)], &[]).result(2).err(), Some(Config::TooFewPlayers(2).into()));
+    }
+
+    #[test]
+    fn each_voter_hangs_one_defendant() {
+        let mut vote = DeathVote::new(BTreeSet::from([1, 2, 3]), Threshold::Plurality).unwrap();
+        assert!(vote.hang(4, 1));
+        assert!(vote.hang(4, 2));
+        assert!(!vote.hang(4, 4));
+        assert!(!vote.hang(5, 9));
+        assert_eq!(vote.tally(10).votes[&1].len(), 0);
+        assert_eq!(vote.dead(10), Some(2));
+    }
+
+    #[test]
+    fn defendants_of_a_duel_do_not_vote() {
+        let mut vote = DeathVote::new(BTreeSet::from([1, 2]), Threshold::Plurality).unwrap();
+        assert!(!vote.hang(1, 2));
+        assert!(!vote.abstain(2));
+        assert!(vote.hang(3, 1));
+    }
+
+    #[test]
+    fn abstentions_outnumbering_the_leader_spare_everybody() {
+        let mut vote = DeathVote::new(BTreeSet::from([1, 2]), Threshold::Plurality).unwrap();
+        vote.hang(3, 1);
+        vote.hang(4, 1);
+        vote.abstain(5);
+        assert_eq!(vote.dead(10), Some(1));
+        vote.abstain(6);
+        assert_eq!(vote.dead(10), None);
+        vote.hang(6, 1);
+        assert_eq!(vote.dead(10), Some(1));
+    }
+
+    #[test]
+    fn a_tie_leaves_several_leaders() {
+        let mut vote = DeathVote::new(BTreeSet::from([1, 2]), Threshold::Fixed(2)).unwrap();
+        for (from, on) in [(3, 1), (4, 1), (5, 2), (6, 2)].iter() {
+            vote.hang(*from, *on);
+        }
+        assert_eq!(vote.leaders(10), vec![1, 2]);
+        assert_eq!(vote.dead(10), None);
+        vote.hang(7, 2);
+        assert_eq!(vote.dead(10), Some(2));
+    }
+}
@@ -1 +1,3 @@
-pub mod ballot;
\ No newline at end of file
+pub mod ballot;
+pub mod tie;
+pub mod voting;
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{scenario::GameRng, waiting::PlayerId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TiePolicy {
+    #[default]
+    Revote,
+    Nobody,
+    Random,
+    LotDraw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Decided {
+    Votes,
+    Policy(TiePolicy),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub hanged: Option<PlayerId>,
+    pub tied: Vec<PlayerId>,
+    pub decided: Decided,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Lots {
+    players: Vec<PlayerId>,
+    fatal: usize,
+    drawn: BTreeMap<PlayerId, usize>,
+}
+
+impl Lots {
+    pub fn new(players: Vec<PlayerId>, rng: &mut GameRng) -> Self {
+        let fatal = rng.gen_range(0..players.len());
+        Self { players, fatal, drawn: BTreeMap::new() }
+    }
+
+    pub fn players(&self) -> &Vec<PlayerId> {
+        &self.players
+    }
+
+    pub fn size(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn contains(&self, id: &PlayerId) -> bool {
+        self.players.contains(id)
+    }
+
+    pub fn pending(&self, id: &PlayerId) -> bool {
+        self.contains(id) && !self.drawn.contains_key(id)
+    }
+
+    pub fn taken(&self, lot: usize) -> bool {
+        self.drawn.values().any(|l| *l == lot)
+    }
+
+    pub fn draw(&mut self, from: PlayerId, lot: usize) -> bool {
+        if !self.pending(&from) || lot >= self.size() || self.taken(lot) {
+            return false
+        }
+        self.drawn.insert(from, lot);
+        true
+    }
+
+    pub fn close(mut self, rng: &mut GameRng) -> PlayerId {
+        let mut left = (0..self.size()).filter(|l| !self.taken(*l)).collect::<Vec<_>>();
+        left.shuffle(rng);
+        for id in self.players.iter() {
+            if !self.drawn.contains_key(id) {
+                if let Some(lot) = left.pop() {
+                    self.drawn.insert(*id, lot);
+                }
+            }
+        }
+
+        self.drawn.iter()
+            .find(|(_, lot)| **lot == self.fatal)
+            .map(|(id, _)| *id)
+            .unwrap_or(self.players[self.fatal])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn each_tied_player_draws_one_free_lot() {
+        let mut rng = GameRng::seed_from_u64(1);
+        let mut lots = Lots::new(vec![4, 7, 9], &mut rng);
+        assert!(lots.draw(4, 2));
+        assert!(!lots.draw(4, 1));
+        assert!(!lots.draw(7, 2));
+        assert!(!lots.draw(7, 3));
+        assert!(!lots.draw(5, 0));
+        assert!(lots.pending(&7));
+        assert!(!lots.pending(&4));
+    }
+
+    #[test]
+    fn the_fatal_lot_is_hanged() {
+        for seed in 0..20 {
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut lots = Lots::new(vec![4, 7, 9], &mut rng);
+            let fatal = lots.fatal;
+            for (lot, id) in [4, 7, 9].iter().copied().enumerate() {
+                lots.draw(id, lot);
+            }
+            assert_eq!(lots.close(&mut rng), [4, 7, 9][fatal]);
+        }
+    }
+
+    #[test]
+    fn undrawn_lots_are_dealt_on_close() {
+        for seed in 0..20 {
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut lots = Lots::new(vec![4, 7, 9], &mut rng);
+            lots.draw(7, 0);
+            let hanged = lots.close(&mut rng);
+            assert!([4, 7, 9].contains(&hanged));
+        }
+    }
+}
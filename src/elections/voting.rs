@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+use super::tie::TiePolicy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Threshold {
+    #[default]
+    Half,
+    StrictMajority,
+    SimpleMajority,
+    Plurality,
+    Fixed(usize),
+}
+
+impl Threshold {
+    pub fn required(&self, living: usize, cast: usize) -> usize {
+        match self {
+            Threshold::Half => (living / 2).max(1),
+            Threshold::StrictMajority => living / 2 + 1,
+            Threshold::SimpleMajority => cast / 2 + 1,
+            Threshold::Plurality => 1,
+            Threshold::Fixed(n) => (*n).max(1),
+        }
+    }
+
+    pub fn fixed(&self) -> Option<usize> {
+        match self {
+            Threshold::Fixed(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn plurality(&self) -> bool {
+        *self == Threshold::Plurality
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Voting {
+    #[serde(default)]
+    pub tie: TiePolicy,
+    #[serde(default)]
+    pub nominate: Threshold,
+    #[serde(default)]
+    pub hang: Threshold,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_count_the_living_or_the_cast() {
+        assert_eq!(Threshold::Half.required(10, 3), 5);
+        assert_eq!(Threshold::Half.required(1, 1), 1);
+        assert_eq!(Threshold::StrictMajority.required(10, 3), 6);
+        assert_eq!(Threshold::SimpleMajority.required(10, 7), 4);
+        assert_eq!(Threshold::SimpleMajority.required(10, 0), 1);
+        assert_eq!(Threshold::Plurality.required(10, 7), 1);
+        assert_eq!(Threshold::Fixed(3).required(10, 7), 3);
+        assert_eq!(Threshold::Fixed(0).required(10, 7), 1);
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{scenario::{Act, Channel, CityState, Power}, waiting::PlayerId};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Error {
+    Configuration(Config),
+    Phase(Act, CityState),
+    Rule(Rule),
+    Lookup(Lookup),
+    Serialization(String),
+    Io(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Config {
+    TooFewPlayers(usize),
+    Players { name: String, min: usize, max: usize, got: usize },
+    Seats { composition: usize, joined: usize },
+    NoMafia,
+    MafiaMajority { mafia: usize, city: usize },
+    NightKillers(usize),
+    CityNightKill,
+    Threshold { required: usize, players: usize },
+    UnknownRole(String),
+    UnknownPower(Power),
+    DuplicateRole(String),
+    DuplicateAbility(Power),
+    NoRole(Vec<Power>),
+    SavedScenario { expected: String, found: String },
+    DuplicateScenario(String),
+    Options { name: String, reason: String },
+    Unfilled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rule {
+    NotAnAction(Power),
+    NoGuess,
+    UnknownRole(String),
+    Unrevealable(Power),
+    NoMessage,
+    NoMeta(Act),
+    Targets { action: Act, min: usize, max: usize, got: usize },
+    SameTargets,
+    SelfTarget(Act),
+    Missing(PlayerId, Power),
+    Out(PlayerId),
+    NoGun(PlayerId),
+    NotOnTrial(PlayerId),
+    Defendant(PlayerId),
+    NobodyOnTrial,
+    NoNominees,
+    Refused(PlayerId, Act),
+    EmptyMessage,
+    LongMessage(usize),
+    Closed(Channel, CityState),
+    MafiaOnly(PlayerId),
+    NoLastWords(PlayerId),
+    NoLot,
+    NoLotDraw,
+    NotDrawing(PlayerId),
+    BadLot(usize),
+    NoCard(PlayerId),
+    Silenced(PlayerId),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lookup {
+    Player(PlayerId),
+    Lobby(String),
+    Game(String),
+    Full(String),
+    Session,
+    Key(String),
+    Path(String),
+    Scenario(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Configuration(config) => write!(f, "{}", config),
+            Error::Phase(action, state) => write!(f, "{:?} is not allowed during {:?}", action, state),
+            Error::Rule(rule) => write!(f, "{}", rule),
+            Error::Lookup(lookup) => write!(f, "{}", lookup),
+            Error::Serialization(reason) => write!(f, "{}", reason),
+            Error::Io(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Config::*;
+        match self {
+            TooFewPlayers(got) => write!(f, "At least 3 players are needed, got {}", got),
+            Players { name, min, max, got } => write!(f, "{} is played by {} to {} players, got {}", name, min, max, got),
+            Seats { composition, joined } => write!(f, "Composition is made for {} players but {} joined", composition, joined),
+            NoMafia => write!(f, "Composition has no mafia"),
+            MafiaMajority { mafia, city } => write!(f, "Mafia wins before the first day with {} mafia against {} citizens", mafia, city),
+            NightKillers(found) => write!(f, "Exactly one player must hold NightKill, found {}", found),
+            CityNightKill => write!(f, "NightKill must be held by a mafia"),
+            Threshold { required, players } => write!(f, "A threshold of {} votes can not be reached by {} players", required, players),
+            UnknownRole(name) => write!(f, "Unknown role {}", name),
+            UnknownPower(power) => write!(f, "{:?} is not given by any registered role", power),
+            DuplicateRole(name) => write!(f, "Role {} is already registered", name),
+            DuplicateAbility(power) => write!(f, "{:?} is already given by another role", power),
+            NoRole(powers) => write!(f, "No registered role has exactly {:?}", powers),
+            SavedScenario { expected, found } => write!(f, "The save is a {} game, not {}", found, expected),
+            DuplicateScenario(name) => write!(f, "Scenario {} is already registered", name),
+            Options { name, reason } => write!(f, "Invalid options for {}: {}", name, reason),
+            Unfilled => write!(f, "The table is not full"),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Rule::*;
+        match self {
+            NotAnAction(power) => write!(f, "{:?} is not an action", power),
+            NoGuess => write!(f, "A guess needs a guessed power or role"),
+            UnknownRole(name) => write!(f, "No role named {} in this game", name),
+            Unrevealable(power) => write!(f, "{:?} can not be revealed", power),
+            NoMessage => write!(f, "Talk needs a message"),
+            NoMeta(action) => write!(f, "{:?} takes no extra data", action),
+            Targets { action, min, max, got } => write!(f, "{:?} needs {} to {} targets, got {}", action, min, max, got),
+            SameTargets => write!(f, "Targets must be different players"),
+            SelfTarget(action) => write!(f, "{:?} can not target yourself", action),
+            Missing(_, power) => write!(f, "You don't have {:?}", power),
+            Out(_) => write!(f, "You are out of the game"),
+            NoGun(_) => write!(f, "You have no gun"),
+            NotOnTrial(id) => write!(f, "Player {} is not on trial", id),
+            Defendant(_) => write!(f, "Defendants can not vote"),
+            NobodyOnTrial => write!(f, "Nobody is on trial"),
+            NoNominees => write!(f, "No nominees found"),
+            Refused(_, action) => write!(f, "{:?} was not accepted", action),
+            EmptyMessage => write!(f, "Message is empty"),
+            LongMessage(max) => write!(f, "Message is longer than {} characters", max),
+            Closed(channel, state) => write!(f, "{:?} channel is closed during {:?}", channel, state),
+            MafiaOnly(_) => write!(f, "Only the mafia can use the mafia channel"),
+            NoLastWords(_) => write!(f, "Last words are only for a player who just left the game"),
+            NoLot => write!(f, "Draw needs a lot number"),
+            NoLotDraw => write!(f, "Nobody is drawing lots"),
+            NotDrawing(_) => write!(f, "You are not drawing a lot"),
+            BadLot(lot) => write!(f, "Lot {} is not available", lot),
+            NoCard(_) => write!(f, "You have no last move card to play"),
+            Silenced(_) => write!(f, "You are silenced for today"),
+        }
+    }
+}
+
+impl fmt::Display for Lookup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lookup::Player(id) => write!(f, "Player {} is not in the game", id),
+            Lookup::Lobby(code) => write!(f, "No lobby with code {}", code),
+            Lookup::Game(code) => write!(f, "No running game with code {}", code),
+            Lookup::Full(code) => write!(f, "Lobby {} is full", code),
+            Lookup::Session => write!(f, "Unknown session token"),
+            Lookup::Key(code) => write!(f, "Invalid moderator key for {}", code),
+            Lookup::Path(path) => write!(f, "Unknown path: {}", path),
+            Lookup::Scenario(name) => write!(f, "No scenario named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Rule> for Error {
+    fn from(rule: Rule) -> Self {
+        Error::Rule(rule)
+    }
+}
+
+impl From<Config> for Error {
+    fn from(config: Config) -> Self {
+        Error::Configuration(config)
+    }
+}
+
+impl From<Lookup> for Error {
+    fn from(lookup: Lookup) -> Self {
+        Error::Lookup(lookup)
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.to_string()
+    }
+}
+
+pub fn io(e: impl fmt::Display) -> Error {
+    Error::Io(e.to_string())
+}
+
+pub fn serialization(e: impl fmt::Display) -> Error {
+    Error::Serialization(e.to_string())
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{elections::voting::Voting, roles::Registry, scenario::*};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(default)]
+    pub powers: Vec<Power>,
+    #[serde(default = "Bundle::single")]
+    pub count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+impl Bundle {
+    pub fn new(powers: Vec<Power>, count: usize) -> Self {
+        Self { powers, count, role: None }
+    }
+
+    pub fn role(name: &str, count: usize) -> Self {
+        Self { powers: vec![], count, role: Some(name.to_owned()) }
+    }
+
+    fn single() -> usize {
+        1
+    }
+
+    fn is_mafia(&self) -> bool {
+        self.powers.contains(&Power::Mafia)
+    }
+}
+
+/// Lobby options shared by every game built on a classic composition.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    #[serde(default)]
+    pub voting: Voting,
+    #[serde(default)]
+    pub last_move: Option<bool>,
+}
+
+impl Options {
+    pub fn apply(&self, composition: Composition) -> Composition {
+        let last_move = self.last_move.unwrap_or(composition.last_move);
+        composition.with_voting(self.voting).with_last_move(last_move)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Composition {
+    bundles: Vec<Bundle>,
+    #[serde(default)]
+    voting: Voting,
+    #[serde(default)]
+    last_move: bool,
+}
+
+impl Composition {
+    pub const MIN_PLAYERS: usize = 6;
+    pub const MAX_PLAYERS: usize = 20;
+
+    pub fn new(bundles: Vec<Bundle>) -> Self {
+        Self { bundles, voting: Voting::default(), last_move: false }
+    }
+
+    pub fn with_voting(mut self, voting: Voting) -> Self {
+        self.voting = voting;
+        self
+    }
+
+    pub fn voting(&self) -> Voting {
+        self.voting
+    }
+
+    pub fn with_last_move(mut self, last_move: bool) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    pub fn last_move(&self) -> bool {
+        self.last_move
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::Serialization(format!("Invalid composition: {}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Serialization(format!("Unable to write composition: {}", e)))
+    }
+
+    pub fn recommended(players: usize) -> Result<Self, Error> {
+        use Power::*;
+        if !(Self::MIN_PLAYERS..=Self::MAX_PLAYERS).contains(&players) {
+            return Err(Config::Players {
+                name: "The recommended composition".to_owned(),
+                min: Self::MIN_PLAYERS,
+                max: Self::MAX_PLAYERS,
+                got: players,
+            }.into())
+        }
+
+        let mafia = players / 3;
+        let city = players - mafia;
+
+        let mut mafia_bundles = vec![
+            vec![Mafia, NightKill, DodgeCommando, Disguise],
+            vec![Mafia, Paralyze],
+            vec![Mafia, Reveal],
+        ];
+        mafia_bundles.truncate(mafia);
+
+        let mut city_bundles = vec![
+            vec![Enquery],
+            vec![Heal],
+            vec![HandFakeGun, HandGun],
+            vec![DayShield, Guard],
+            vec![ShotOnKill],
+        ];
+        city_bundles.truncate(city - 2);
+
+        let mut bundles = Vec::new();
+        let plain_mafia = mafia - mafia_bundles.len();
+        let plain_city = city - city_bundles.len();
+        bundles.extend(mafia_bundles.into_iter().map(|p| Bundle::new(p, 1)));
+        if plain_mafia > 0 {
+            bundles.push(Bundle::new(vec![Mafia], plain_mafia));
+        }
+        bundles.extend(city_bundles.into_iter().map(|p| Bundle::new(p, 1)));
+        bundles.push(Bundle::new(vec![], plain_city));
+
+        Ok(Self::new(bundles))
+    }
+
+    pub fn resolve(mut self, registry: &Registry) -> Result<Self, Error> {
+        for bundle in self.bundles.iter_mut() {
+            if let Some(name) = &bundle.role {
+                let role = registry.role(name)
+                    .ok_or_else(|| Config::UnknownRole(name.clone()))?;
+                bundle.powers = role.powers();
+            }
+        }
+
+        let unknown = self.bundles.iter()
+            .flat_map(|b| b.powers.iter())
+            .find(|p| !registry.knows(p));
+        if let Some(power) = unknown {
+            return Err(Config::UnknownPower(*power).into())
+        }
+
+        for bundle in self.bundles.iter_mut().filter(|b| b.role.is_none()) {
+            let role = registry.find(&bundle.powers)
+                .ok_or_else(|| Config::NoRole(bundle.powers.clone()))?;
+            bundle.role = Some(role.name().to_owned());
+        }
+        Ok(self)
+    }
+
+    pub fn players(&self) -> usize {
+        self.bundles.iter().map(|b| b.count).sum()
+    }
+
+    fn count(&self, power: &Power) -> usize {
+        self.bundles.iter()
+            .filter(|b| b.powers.contains(power))
+            .map(|b| b.count)
+            .sum()
+    }
+
+    pub fn validate(&self, players: usize) -> Result<(), Error> {
+        if players < 3 {
+            return Err(Config::TooFewPlayers(players).into())
+        }
+
+        if self.players() != players {
+            return Err(Config::Seats { composition: self.players(), joined: players }.into())
+        }
+
+        let mafia = self.count(&Power::Mafia);
+        let city = players - mafia;
+        if mafia == 0 {
+            return Err(Config::NoMafia.into())
+        }
+        if mafia >= city {
+            return Err(Config::MafiaMajority { mafia, city }.into())
+        }
+
+        if self.count(&Power::NightKill) != 1 {
+            return Err(Config::NightKillers(self.count(&Power::NightKill)).into())
+        }
+        if self.bundles.iter().any(|b| b.powers.contains(&Power::NightKill) && !b.is_mafia()) {
+            return Err(Config::CityNightKill.into())
+        }
+
+        let fixed = [self.voting.nominate, self.voting.hang].iter().filter_map(|t| t.fixed()).max();
+        if let Some(required) = fixed.filter(|n| *n > players) {
+            return Err(Config::Threshold { required, players }.into())
+        }
+
+        Ok(())
+    }
+
+    pub fn bundles(&self) -> Vec<(Option<String>, Vec<Power>)> {
+        self.bundles.iter()
+            .flat_map(|b| std::iter::repeat_n((b.role.clone(), b.powers.clone()), b.count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elections::voting::Threshold;
+
+    use super::*;
+
+    #[test]
+    fn fixed_thresholds_fit_the_table() {
+        let voting = |hang| Voting { hang, ..Voting::default() };
+        let composition = |hang| Composition::recommended(10).unwrap().with_voting(voting(hang));
+        assert_eq!(composition(Threshold::Fixed(10)).validate(10), Ok(()));
+        assert_eq!(composition(Threshold::Fixed(11)).validate(10), Err(Config::Threshold { required: 11, players: 10 }.into()));
+    }
+}
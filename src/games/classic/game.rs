@@ -1,71 +1,217 @@
+use std::{collections::{BTreeMap, BTreeSet}};
 
-use std::{collections::BTreeMap};
+use serde::{Deserialize, Serialize};
 
-use crate::{in_memory_room::InMemoryRoom, room::{Room, Spells}, scenario::*, waiting::{GodWayRef, PlayerId}};
+use crate::{cards::{Card, Deck, Hand}, night::{Effect, Kill}, in_memory_room::InMemoryRoom, observer::{Sight, Watch, WatcherRef}, one_to_one_spells::OneToOneSpells, roles::{Census, Faction, Registry}, room::Room, scenario::*, waiting::{connect, GodWayRef, OracleRef, PlayerId}};
 
-use super::{play::*};
-use crate::elections::ballot::Ballots;
-use rand::{prelude::SliceRandom, thread_rng};
+use super::{composition::{Composition, Options}, play::*, roles};
+use crate::games::registry::{Description, Entry, Game};
+use crate::elections::{ballot::{Ballots, DeathVote}, tie::{Decided, Lots, TiePolicy, Verdict}, voting::Voting};
+use rand::{prelude::SliceRandom, SeedableRng};
 
+#[derive(Serialize, Deserialize)]
 pub struct Classic {
     room: InMemoryRoom,
+    strategy: Play,
     state: CityState,
     events: Vec<(Day, CityState, Declaration)>,
-    enquery: u8,
     day: usize,
+    ballots: Option<Ballots>,
+    death_vote: Option<DeathVote>,
+    spells: OneToOneSpells,
+    acted: BTreeSet<PlayerId>,
+    rng: GameRng,
+    roles: BTreeMap<PlayerId, Vec<Power>>,
+    triggers: Vec<(Day, Trigger)>,
+    #[serde(skip)]
+    departed: BTreeMap<PlayerId, GodWayRef>,
+    voting: Voting,
+    revote: bool,
+    lots: Option<Lots>,
+    #[serde(default)]
+    hanged: BTreeSet<PlayerId>,
+    #[serde(default)]
+    deck: Option<Deck>,
+    #[serde(default)]
+    hand: Option<Hand>,
+    #[serde(default)]
+    silenced: BTreeSet<PlayerId>,
+    #[serde(default)]
+    handcuffed: BTreeSet<PlayerId>,
+    #[serde(default)]
+    graveyard: BTreeMap<PlayerId, (Option<String>, Vec<Power>)>,
+    #[serde(skip)]
+    ghosts: BTreeMap<PlayerId, GodWayRef>,
+    #[serde(default = "Classic::name")]
+    scenario: String,
+    #[serde(skip)]
+    registry: Registry,
 }
 
+const MAX_CHAT: usize = 280;
+
 impl Classic {
-    pub fn new(players: BTreeMap<PlayerId, GodWayRef>) -> Result<Self, Error> {
-        if players.len() != 10 {
-            return Err("Invalid number of players".to_owned())
-        }
+    pub const NAME: &'static str = "classic";
+
+    fn name() -> String {
+        Self::NAME.to_owned()
+    }
+
+    pub fn new(players: BTreeMap<PlayerId, GodWayRef>, composition: Composition, seed: u64) -> Result<Self, Error> {
+        Self::with_registry(players, composition, seed, Self::NAME, roles::registry())
+    }
+
+    pub fn with_registry(players: BTreeMap<PlayerId, GodWayRef>, composition: Composition, seed: u64, scenario: &str, registry: Registry) -> Result<Self, Error> {
+        let composition = composition.resolve(&registry)?;
+        composition.validate(players.len())?;
 
         let mut res = Self {
             room: InMemoryRoom::new(players),
+            strategy: Play::new(),
             state: CityState::Debate,
             events: vec![],
-            enquery: 3,
             day: 0,
+            ballots: None,
+            death_vote: None,
+            spells: OneToOneSpells::new(),
+            acted: BTreeSet::new(),
+            rng: GameRng::seed_from_u64(seed),
+            roles: BTreeMap::new(),
+            triggers: vec![],
+            departed: BTreeMap::new(),
+            voting: composition.voting(),
+            revote: false,
+            lots: None,
+            hanged: BTreeSet::new(),
+            deck: None,
+            hand: None,
+            silenced: BTreeSet::new(),
+            handcuffed: BTreeSet::new(),
+            graveyard: BTreeMap::new(),
+            ghosts: BTreeMap::new(),
+            scenario: scenario.to_owned(),
+            registry,
         };
-        res.assign_roles();
+        if composition.last_move() {
+            res.deck = Some(Deck::new(&mut res.rng));
+        }
+        res.declare(Declaration::Seed(seed));
+        res.declare(Declaration::Seats(res.room.numbers()));
+        res.assign_roles(&composition);
+        res.ballots = Some(res.make_ballot());
+        res.declare(Declaration::StateChanged(res.state.clone()));
         Ok(res)
     }
 
-    fn assign(&mut self, players: &mut Vec<PlayerId>, powers: Vec<Power>) {
+    pub fn save(&self) -> Result<String, Error> {
+        serde_json::to_string(self)
+            .map_err(|e| Error::Serialization(format!("Unable to save the game: {}", e)))
+    }
+
+    pub fn restore(json: &str) -> Result<Self, Error> {
+        Self::restore_with(json, Self::NAME, roles::registry())
+    }
+
+    /// Restores a save of `scenario`, whose roles come from `registry`.
+    pub fn restore_with(json: &str, scenario: &str, registry: Registry) -> Result<Self, Error> {
+        let mut game: Self = serde_json::from_str(json)
+            .map_err(|e| Error::Serialization(format!("Unable to restore the game: {}", e)))?;
+        if game.scenario != scenario {
+            return Err(Config::SavedScenario { expected: scenario.to_owned(), found: game.scenario }.into())
+        }
+        game.registry = registry;
+        Ok(game)
+    }
+
+    pub fn entry() -> Entry {
+        let defaults = Options { last_move: Some(false), ..Options::default() };
+        Entry {
+            description: Description {
+                name: Self::NAME.to_owned(),
+                players: Composition::MIN_PLAYERS..=Composition::MAX_PLAYERS,
+                roles: roles::registry().roles().map(|r| r.name().to_owned()).collect(),
+                options: serde_json::to_value(defaults).unwrap_or_default(),
+            },
+            validate: |description, options| description.options::<Options>(options).map(|_| ()),
+            build: |description, players, options, seed| {
+                let options: Options = description.options(options)?;
+                let composition = options.apply(Composition::recommended(players.len())?);
+                Ok(Box::new(Classic::new(players, composition, seed)?))
+            },
+            restore: |json| Ok(Box::new(Classic::restore(json)?)),
+        }
+    }
+
+    pub fn scenario(&self) -> &str {
+        &self.scenario
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn reattach(&mut self, id: &PlayerId) -> Option<OracleRef> {
+        if let Some(oracle) = self.room.attach(id) {
+            return Some(oracle)
+        }
+        if !self.graveyard.contains_key(id) {
+            return None
+        }
+        let (channel, oracle) = connect();
+        match self.departed.get_mut(id) {
+            Some(connection) => *connection = channel,
+            None => { self.ghosts.insert(*id, channel); },
+        }
+        Some(oracle)
+    }
+
+    pub fn detach(&mut self, id: &PlayerId, oracle: OracleRef) -> bool {
+        if self.room.numbers().contains(id) {
+            return self.room.detach(id, oracle)
+        }
+        self.ghosts.remove(id);
+        self.graveyard.contains_key(id)
+    }
+
+    pub fn observe(&mut self, sight: Sight) -> WatcherRef {
+        let history = self.events.iter()
+            .map(|(day, state, d)| Watch::Declared(*day, state.clone(), d.clone()))
+            .collect();
+        self.room.observe(sight, history)
+    }
+
+    fn assign(&mut self, players: &mut Vec<PlayerId>, role: Option<String>, powers: Vec<Power>) {
         let id = players.pop().unwrap();
         assert!(self.room.assign(&id, powers.clone()));
+        if let Some(role) = role {
+            self.room.cast(&id, &role);
+        }
+        self.roles.insert(id, powers);
     }
 
-    fn assign_roles(&mut self) {
-        use Power::*;
+    fn assign_roles(&mut self, composition: &Composition) {
         let mut players = self.room.numbers();
-        players.shuffle(&mut thread_rng());
+        players.shuffle(&mut self.rng);
+
+        for (role, powers) in composition.bundles() {
+            self.assign(&mut players, role, powers);
+        }
+    }
 
-        self.assign(&mut players, vec![Mafia, NightKill, DodgeCommando, Disguise]);
-        self.assign(&mut players, vec![Mafia, Paralyze]);
-        self.assign(&mut players, vec![Mafia, Reveal]);
+    pub fn roles(&self) -> &BTreeMap<PlayerId, Vec<Power>> {
+        &self.roles
+    }
 
-        self.assign(&mut players, vec![HandFakeGun, HandGun]);
-        self.assign(&mut players, vec![DayShield, Guard]);
-        self.assign(&mut players, vec![Heal]);
-        self.assign(&mut players, vec![ShotOnKill]);
-        self.assign(&mut players, vec![Enquery]);
-        self.assign(&mut players, vec![]);
-        self.assign(&mut players, vec![]);
+    pub fn alive(&self) -> Vec<PlayerId> {
+        self.room.numbers()
     }
 
-    fn enquery(&mut self) -> Option<usize> {
-        if self.enquery == 0 {
-            None
-        } else {
-            self.enquery -= 1;
-            Some(self.room.count(&Power::Mafia))
-        }
+    pub fn triggers(&self) -> &Vec<(Day, Trigger)> {
+        &self.triggers
     }
 
     fn make_ballot(&self) -> Ballots {
-        Ballots::new(self.room.total())
+        Ballots::new(self.voting.nominate, self.voting.hang)
     }
 
     fn first_id_by_power(&mut self, power: &Power) -> Option<PlayerId> {
@@ -78,22 +224,40 @@ impl Classic {
 
         if let Some(id) = pid {
             self.room.assign(&id, vec![Power::NightKill]);
+            self.room.text_to(&id, HolyMessage::YouAreBoss);
             return true
         }
         false
     }
     
     fn kick_out(&mut self, id: &PlayerId) -> bool {
-        let out = self.room.remove(id);
+        if !self.room.numbers().contains(id) {
+            return false
+        }
+
+        let (role, out, connection) = match self.room.evict(id) {
+            Some(player) => player,
+            None => return false
+        };
         if out.contains(&Power::NightKill) {
             self.pass_night_kill();
         }
+        self.graveyard.insert(*id, (role, out));
+        self.departed.insert(*id, connection);
+        self.declare(Declaration::Out(*id));
+        self.settle();
+        true
+    }
 
-        if out.len() >= 1 {
-            self.declare(Declaration::Out(id.clone()));
-            return true
+    fn settle(&mut self) {
+        if let CityState::Done(_) = self.state {
+            return
+        }
+        let game = self.game_state();
+        if game != State::Undecided {
+            self.state = CityState::Done(game);
+            self.declare(Declaration::StateChanged(self.state.clone()));
         }
-        false
     }
 
     fn is_it(&self, state: CityState) -> bool {
@@ -101,66 +265,467 @@ impl Classic {
     }
 
     fn declare(&mut self, s: Declaration) {
-        self.events.push((self.day, self.state, s));
+        if s.public() {
+            self.room.broadcast(HolyMessage::Declared(s.clone()));
+            self.haunt(HolyMessage::Declared(s.clone()));
+        }
+        self.room.watch(Watch::Declared(self.day, self.state.clone(), s.clone()));
+        self.events.push((self.day, self.state.clone(), s));
     }
     
+    fn haunt(&mut self, msg: HolyMessage) {
+        for connection in self.departed.values_mut().chain(self.ghosts.values_mut()) {
+            connection.tell(msg.clone());
+        }
+    }
+
     fn on_shooting(&mut self, res: ShootingResult) {
         match res {
             ShootingResult::EmptyGun(id) => self.declare(Declaration::FakeGun(id)),
             ShootingResult::Killed(id) => {
-                if !self.room.has(&id, &Power::DayShield) && self.kick_out(&id) {
-                    self.declare(Declaration::Out(id));
+                if !self.room.has(&id, &Power::DayShield) {
+                    self.kick_out(&id);
                 }
             },
             ShootingResult::NotAllowed => {}
         }
     }
 
-    fn darkness(&mut self, spells: &impl Spells) -> Result<NightResult, Error> {
-        // let spells = self.room.read_all();
-        // let s = OneToOneSpells::new();
-        // self.strategy.apply_night(&mut self.room, s)
-        todo!()
+    fn darkness(&mut self) -> NightResult {
+        let mut spells = std::mem::take(&mut self.spells);
+        for id in std::mem::take(&mut self.handcuffed) {
+            spells.cancel(&id);
+        }
+        self.strategy.apply_night(&mut self.room, spells, &self.registry)
+    }
+
+    fn revive(&mut self, id: PlayerId) {
+        let (role, powers) = match self.graveyard.remove(&id) {
+            Some(grave) => grave,
+            None => return
+        };
+        let connection = self.ghosts.remove(&id)
+            .or_else(|| self.departed.remove(&id))
+            .filter(|c| !c.is_closed());
+        if self.room.seat(id, role, powers, connection) {
+            self.hanged.remove(&id);
+            self.declare(Declaration::Back(id));
+        }
     }
 
-    fn sunrise(&mut self, n: &impl News) {
+    fn sunrise(&mut self, n: &NightResult) {
+        for (id, faction) in n.recruited() {
+            if let Some(role) = self.registry.plain(*faction) {
+                let (name, powers) = (role.name().to_owned(), role.powers());
+                self.room.cast(id, &name);
+                self.room.assign(id, powers);
+            }
+        }
+
         for n in n.kicked_out() {
             self.kick_out(n);
         }
+
+        for id in n.revived().clone() {
+            self.revive(id);
+        }
+
+        for id in n.exposed().clone() {
+            if self.room.numbers().contains(&id) {
+                self.declare(Declaration::Exposed(id));
+            }
+        }
+
+        for (id, msg) in n.messages().iter() {
+            self.room.text_to(id, msg.clone());
+        }
+
+        for id in self.strategy.armed() {
+            self.room.text_to(&id, HolyMessage::YouHaveGun);
+        }
     }
 
-    fn sunset(&mut self, d: &impl DeathBallot) {
-        if let Some(ref id) = d.dead() {
+    fn sunset(&mut self, verdict: Verdict) {
+        let hanged = verdict.hanged;
+        self.declare(Declaration::Verdict(verdict));
+        if let Some(ref id) = hanged {
             if !self.room.has(id, &Power::DayShield) {
+                self.hanged.insert(*id);
+                let role = self.room.role(id).map(str::to_owned);
+                let mut powers = self.room.kinks(id);
                 self.kick_out(id);
+                if self.room.count(&Power::NightKill) > 0 {
+                    powers.retain(|p| *p != Power::NightKill);
+                }
+                self.deal(*id, role, powers);
             }
         }
     }
 
-    fn defend(&self) -> Option<Ballots> {
-        if self.is_it(CityState::Defend) {
-            return Some(self.make_ballot());
+    fn deal(&mut self, holder: PlayerId, role: Option<String>, powers: Vec<Power>) {
+        if let CityState::Done(_) = self.state {
+            return
         }
-        None
+        let card = match self.deck.as_mut().and_then(|d| d.draw()) {
+            Some(card) => card,
+            None => return
+        };
+        self.hand = Some(Hand { holder, card, role, powers });
+        self.declare(Declaration::CardDrawn(holder, card));
     }
 
-    fn game_state(&self) -> State {
-        let mafia = self.room.count(&Power::Mafia);
-        let city = self.room.total() - mafia;
-        if city <= mafia {
-            State::MafiaWon
-        } else if mafia == 0 {
-            State::CityWon
-        } else {
-            State::Undecided
+    fn fold(&mut self) {
+        if let Some(hand) = self.hand.take() {
+            self.declare(Declaration::CardPlayed(hand.holder, hand.card, vec![]));
         }
     }
+
+    fn on_card(&mut self, from: PlayerId, pray: Pray) -> Result<(), Error> {
+        pray.is_consistent()?;
+        let hand = match &self.hand {
+            Some(hand) if hand.holder == from => hand.clone(),
+            _ => return Err(Rule::NoCard(from).into())
+        };
+        if pray.query.is_empty() {
+            self.fold();
+            self.room.watch(Watch::Prayed(from, pray));
+            return Ok(())
+        }
+        pray.has_targets(hand.card.targets())?;
+        let alive = self.room.numbers();
+        if let Some(on) = pray.query.iter().find(|on| !alive.contains(on)) {
+            return Err(Lookup::Player(*on).into())
+        }
+
+        self.hand = None;
+        self.declare(Declaration::CardPlayed(from, hand.card, pray.query.clone()));
+        match (hand.card, pray.query.as_slice()) {
+            (Card::SilenceOfTheLambs, on) => self.silenced.extend(on),
+            (Card::Handcuffs, on) => self.handcuffed.extend(on),
+            (Card::FaceOff, &[on]) => {
+                let (role, theirs) = self.room.replace(&on, hand.role, hand.powers).unwrap_or_default();
+                if theirs.contains(&Power::NightKill) && self.room.count(&Power::NightKill) == 0 {
+                    self.pass_night_kill();
+                }
+                self.graveyard.insert(from, (role, theirs));
+                self.settle();
+            },
+            (Card::BeautifulMind, &[on]) if self.is_killer(&on) => {
+                self.graveyard.insert(from, (hand.role, hand.powers));
+                self.revive(from);
+            },
+            _ => {}
+        }
+        self.room.watch(Watch::Prayed(from, pray));
+        Ok(())
+    }
+
+    fn close_hang(&mut self) -> CityState {
+        if let Some(lots) = self.lots.take() {
+            let tied = lots.players().clone();
+            let hanged = lots.close(&mut self.rng);
+            self.sunset(Verdict { hanged: Some(hanged), tied, decided: Decided::Policy(TiePolicy::LotDraw) });
+            return CityState::Night
+        }
+
+        let vote = match self.death_vote.take() {
+            Some(vote) => vote,
+            None => return CityState::Night
+        };
+        let living = self.room.total();
+        self.announce(HolyMessage::Tally(vote.tally(living)));
+
+        let revote = std::mem::take(&mut self.revote);
+        let decided = if revote { Decided::Policy(TiePolicy::Revote) } else { Decided::Votes };
+        let tied = vote.leaders(living);
+        let verdict = match tied.as_slice() {
+            [] => Verdict { hanged: None, tied: vec![], decided },
+            [one] => Verdict { hanged: Some(*one), tied: vec![], decided },
+            _ if revote => Verdict { hanged: None, tied, decided },
+            _ => match self.voting.tie {
+                TiePolicy::Revote => {
+                    self.death_vote = vote.revote(tied.clone()).ok();
+                    self.revote = true;
+                    self.declare(Declaration::OnTrial(tied));
+                    return CityState::Hang
+                },
+                TiePolicy::LotDraw => {
+                    self.lots = Some(Lots::new(tied.clone(), &mut self.rng));
+                    self.declare(Declaration::LotDraw(tied));
+                    return CityState::Hang
+                },
+                TiePolicy::Random => {
+                    let hanged = tied.choose(&mut self.rng).copied();
+                    Verdict { hanged, tied, decided: Decided::Policy(TiePolicy::Random) }
+                },
+                TiePolicy::Nobody => Verdict { hanged: None, tied, decided: Decided::Policy(TiePolicy::Nobody) },
+            }
+        };
+        self.sunset(verdict);
+        CityState::Night
+    }
+
+    fn announce(&mut self, msg: HolyMessage) {
+        self.room.broadcast(msg.clone());
+        self.haunt(msg.clone());
+        self.room.watch(Watch::Broadcast(msg));
+    }
+
+    fn reject(&mut self, id: PlayerId, reason: Error) {
+        let msg = HolyMessage::Rejected { reason: reason.to_string() };
+        if !self.room.text_to(&id, msg.clone()) {
+            let ghosts = &mut self.ghosts;
+            if let Some(connection) = self.departed.get_mut(&id).or_else(|| ghosts.get_mut(&id)) {
+                connection.tell(msg);
+            }
+        }
+    }
+
+    fn on_talk(&mut self, from: PlayerId, pray: Pray) -> Result<(), Error> {
+        pray.is_consistent()?;
+        let (channel, text) = match pray.meta {
+            Some(Meta::Say(channel, text)) => (channel, text),
+            _ => return Err(Rule::NoMessage.into())
+        };
+        if text.trim().is_empty() {
+            return Err(Rule::EmptyMessage.into())
+        }
+        if text.chars().count() > MAX_CHAT {
+            return Err(Rule::LongMessage(MAX_CHAT).into())
+        }
+
+        let alive = self.room.numbers().contains(&from);
+        let msg = HolyMessage::Said(from, channel, text.clone());
+        match (&self.state, channel) {
+            (_, Channel::Public | Channel::Mafia) if !alive => {
+                return Err(Rule::Out(from).into())
+            },
+            (CityState::Debate | CityState::Defend, Channel::Public) if self.silenced.contains(&from) => {
+                return Err(Rule::Silenced(from).into())
+            },
+            (CityState::Debate | CityState::Defend, Channel::Public) => {
+                self.room.broadcast(msg.clone());
+                self.haunt(msg);
+            },
+            (CityState::Night, Channel::Mafia) if self.room.has(&from, &Power::Mafia) => {
+                for id in self.room.by_power(&Power::Mafia) {
+                    self.room.text_to(&id, msg.clone());
+                }
+            },
+            (CityState::Night, Channel::Mafia) => {
+                return Err(Rule::MafiaOnly(from).into())
+            },
+            (_, Channel::LastWords) if self.departed.contains_key(&from) => {
+                if let Some(connection) = self.departed.remove(&from) {
+                    self.ghosts.insert(from, connection);
+                }
+                self.room.broadcast(msg.clone());
+                self.haunt(msg);
+            },
+            (_, Channel::LastWords) => {
+                return Err(Rule::NoLastWords(from).into())
+            },
+            (state, channel) => {
+                return Err(Rule::Closed(channel, state.clone()).into())
+            }
+        }
+        self.room.watch(Watch::Said(from, channel, text));
+        Ok(())
+    }
+
+    fn validate(&self, from: PlayerId, pray: &Pray) -> Result<(), Error> {
+        pray.is_consistent()?;
+        let alive = self.room.numbers();
+        if !alive.contains(&from) {
+            return Err(Rule::Out(from).into())
+        }
+
+        let ability = match pray.action {
+            Act::Use(power) => Some(self.registry.ability(&power).ok_or(Rule::NotAnAction(power))?),
+            _ => None,
+        };
+        if let Act::Use(power) = pray.action {
+            if !self.room.has(&from, &power) {
+                return Err(Rule::Missing(from, power).into())
+            }
+        }
+        let graveyard = ability.is_some_and(|a| a.targeting.graveyard);
+        let missing = pray.query.iter().find(|on| match graveyard {
+            true => !self.graveyard.contains_key(on),
+            false => !alive.contains(on),
+        });
+        if let Some(on) = missing {
+            return Err(Lookup::Player(*on).into())
+        }
+
+        if let Some(ability) = ability {
+            pray.has_targets(ability.targeting.allowed(alive.len()))?;
+            match (&pray.meta, ability.effect) {
+                (Some(Meta::Has(_)), Effect::Kill(Kill::Guess)) => {},
+                (Some(Meta::Role(name)), Effect::Kill(Kill::Sense)) if self.registry.role(name).is_none() => {
+                    return Err(Rule::UnknownRole(name.clone()).into())
+                },
+                (Some(Meta::Role(_)), Effect::Kill(Kill::Sense)) => {},
+                (_, Effect::Kill(Kill::Guess | Kill::Sense)) => return Err(Rule::NoGuess.into()),
+                (None, _) => {},
+                (Some(_), _) => return Err(Rule::NoMeta(pray.action).into()),
+            }
+        }
+        let on_self = ability.is_some_and(|a| {
+            a.targeting.on_self && a.targeting.self_cost.is_none_or(|cost| self.room.has(&from, &cost))
+        });
+        if !on_self && pray.query.contains(&from) {
+            return Err(Rule::SelfTarget(pray.action).into())
+        }
+
+        let allowed = match pray.action {
+            Act::Nominate => self.is_it(CityState::Debate),
+            Act::Vote | Act::Draw => self.is_it(CityState::Hang),
+            Act::Shoot => self.is_it(CityState::Debate) || self.is_it(CityState::Defend),
+            Act::Use(_) => self.is_it(CityState::Night),
+            Act::Talk | Act::LastMove => true,
+        };
+        if !allowed {
+            return Err(Error::Phase(pray.action, self.state.clone()))
+        }
+
+        match pray.action {
+            Act::Shoot if !self.strategy.armed().contains(&from) => {
+                Err(Rule::NoGun(from).into())
+            },
+            Act::Vote => match &self.death_vote {
+                Some(vote) if pray.query.first().is_some_and(|on| !vote.list().contains(on)) => {
+                    Err(Rule::NotOnTrial(pray.query[0]).into())
+                },
+                Some(vote) if vote.list().len() == 2 && vote.list().contains(&from) => {
+                    Err(Rule::Defendant(from).into())
+                },
+                Some(_) => Ok(()),
+                None => Err(Rule::NobodyOnTrial.into()),
+            },
+            Act::Draw => match (&self.lots, &pray.meta) {
+                (None, _) => Err(Rule::NoLotDraw.into()),
+                (Some(lots), _) if !lots.pending(&from) => Err(Rule::NotDrawing(from).into()),
+                (Some(lots), Some(Meta::Lot(lot))) if *lot == 0 || *lot > lots.size() || lots.taken(lot - 1) => {
+                    Err(Rule::BadLot(*lot).into())
+                },
+                _ => Ok(())
+            },
+            _ => Ok(())
+        }
+    }
+
+    fn on_act(&mut self, from: PlayerId, pray: Pray) -> Result<(), Error> {
+        self.validate(from, &pray)?;
+
+        let seen = pray.clone();
+        let action = pray.action;
+        let accepted = match (pray.action, pray.query.as_slice()) {
+            (Act::Shoot, &[on]) => {
+                let res = self.strategy.shoot(&from, on);
+                self.on_shooting(res);
+                true
+            },
+            (Act::Nominate, &[on]) => {
+                self.ballots.as_mut().is_some_and(|b| b.nominate(from, on))
+            },
+            (Act::Nominate, &[]) => {
+                self.ballots.as_mut().is_some_and(|b| b.abstain(from))
+            },
+            (Act::Vote, &[on]) => {
+                self.death_vote.as_mut().is_some_and(|d| d.hang(from, on))
+            },
+            (Act::Vote, &[]) => {
+                self.death_vote.as_mut().is_some_and(|d| d.abstain(from))
+            },
+            (Act::Draw, _) => match (&mut self.lots, &pray.meta) {
+                (Some(lots), Some(Meta::Lot(lot))) => lots.draw(from, lot - 1),
+                _ => false
+            },
+            _ => self.spells.cast(from, pray),
+        };
+
+        if !accepted {
+            return Err(Rule::Refused(from, action).into())
+        }
+        match (action, seen.query.as_slice()) {
+            (Act::Nominate, &[on]) => self.announce(HolyMessage::Nominated { from, on }),
+            (Act::Vote, &[on]) => self.announce(HolyMessage::Voted { from, on }),
+            (Act::Nominate | Act::Vote, &[]) => self.announce(HolyMessage::Abstained { from, action }),
+            (Act::Draw, _) => if let Some(Meta::Lot(lot)) = seen.meta {
+                self.announce(HolyMessage::Drew { from, lot })
+            },
+            _ => {}
+        }
+        self.acted.insert(from);
+        self.room.watch(Watch::Prayed(from, seen));
+        Ok(())
+    }
+
+    fn on_pray(&mut self, from: PlayerId, pray: Pray) {
+        let res = match pray.action {
+            Act::Talk => self.on_talk(from, pray),
+            Act::LastMove => self.on_card(from, pray),
+            _ => self.on_act(from, pray),
+        };
+
+        if let Err(reason) = res {
+            self.reject(from, reason);
+        }
+    }
+
+    fn must_act(&self, id: &PlayerId) -> bool {
+        match self.state {
+            CityState::Hang => match &self.lots {
+                Some(lots) => lots.pending(id),
+                None => self.death_vote.as_ref()
+                    .is_some_and(|d| !d.list().contains(id)),
+            },
+            CityState::Night => !self.handcuffed.contains(id) && self.registry.required(&self.room.kinks(id)),
+            _ => false
+        }
+    }
+
+    /// Debate and Defend are open talks, so only a deadline or the host closes them.
+    fn can_advance(&self) -> bool {
+        let living = self.room.numbers();
+        let ready = match self.state {
+            CityState::Debate | CityState::Defend => false,
+            _ => living.iter().all(|id| !self.must_act(id) || self.acted.contains(id)),
+        };
+        ready && self.hand.is_none()
+    }
+
+    fn role(&self, id: &PlayerId) -> Option<&str> {
+        self.room.role(id)
+            .or_else(|| self.graveyard.get(id).and_then(|(role, _)| role.as_deref()))
+    }
+
+    fn faction(&self, id: &PlayerId) -> Faction {
+        self.role(id)
+            .and_then(|role| self.registry.faction(role))
+            .unwrap_or(Faction::City)
+    }
+
+    /// Whether the player's role is the one dealt NightKill, however the gun has moved since.
+    fn is_killer(&self, id: &PlayerId) -> bool {
+        self.role(id)
+            .and_then(|role| self.registry.role(role))
+            .is_some_and(|role| role.powers().contains(&Power::NightKill))
+    }
+
+    fn game_state(&self) -> State {
+        let alive = self.room.numbers();
+        let hanged = self.hanged.iter().map(|id| self.faction(id)).collect();
+        let census = Census::new(alive.iter().map(|id| self.faction(id)), hanged);
+        census.state()
+    }
 }
 
 
 impl Scenario for Classic {
     fn state(&self) -> CityState {
-        self.state
+        self.state.clone()
     }
 
     fn day(&self) -> usize {
@@ -168,28 +733,638 @@ impl Scenario for Classic {
     }
 
     fn next(&mut self) -> CityState {
-        let game = self.game_state();
-        if game != State::Undecided {
-            return CityState::Done(game);
-        }
-        self.state = match self.state {
-            CityState::Debate => CityState::Defend,
+        let departed = std::mem::take(&mut self.departed);
+        self.ghosts.extend(departed);
+        let graveyard = &self.graveyard;
+        self.ghosts.retain(|id, c| graveyard.contains_key(id) && !c.is_closed());
+        self.fold();
+        let next = match self.state.clone() {
+            CityState::Debate => {
+                let ballots = self.ballots.take();
+                if let Some(b) = &ballots {
+                    self.announce(HolyMessage::Tally(b.tally(self.room.total())));
+                }
+                let living = self.room.total();
+                self.death_vote = match ballots.map(|b| b.result(living)).transpose() {
+                    Ok(vote) => vote.flatten(),
+                    Err(e) => {
+                        self.room.watch(Watch::Fault(e));
+                        None
+                    },
+                };
+                if let Some(vote) = &self.death_vote {
+                    let defendants = vote.list().iter().cloned().collect();
+                    self.declare(Declaration::OnTrial(defendants));
+                    CityState::Defend
+                } else {
+                    CityState::Night
+                }
+            },
             CityState::Defend => CityState::Hang,
-            CityState::Hang => CityState::Night,
+            CityState::Hang => self.close_hang(),
             CityState::Night => {
+                let news = self.darkness();
+                let day = self.day;
+                self.triggers.extend(news.triggers().iter().map(|t| (day, *t)));
+                self.room.watch(Watch::Night {
+                    day,
+                    removed: news.kicked_out().iter().cloned().collect(),
+                    messages: news.messages().iter().map(|(id, m)| (*id, m.clone())).collect(),
+                    triggers: news.triggers().iter().filter_map(|t| serde_json::to_value(t).ok()).collect(),
+                });
+                self.sunrise(&news);
                 self.day += 1;
                 CityState::Debate
             },
-            d@CityState::Done {..} => d
+            d@CityState::Done {..} => return d
         };
-        self.declare(Declaration::StateChanged(self.state));
-        self.state
+        if let CityState::Done(_) = self.state {
+            return self.state.clone()
+        }
+        self.state = next;
+        self.acted.clear();
+
+        let game = self.game_state();
+        if game != State::Undecided {
+            self.state = CityState::Done(game);
+        } else if self.is_it(CityState::Debate) {
+            self.ballots = Some(self.make_ballot());
+        } else if self.is_it(CityState::Night) {
+            self.silenced.clear();
+        }
+        self.declare(Declaration::StateChanged(self.state.clone()));
+        self.state.clone()
     }
 
-    fn process(&mut self) {
+    fn process(&mut self) -> bool {
+        for id in self.room.numbers() {
+            for pray in self.room.messages(&id) {
+                self.on_pray(id, pray);
+            }
+        }
+
+        let mut last = Vec::new();
+        for (id, connection) in self.departed.iter_mut().chain(self.ghosts.iter_mut()) {
+            while let Some(pray) = connection.read() {
+                last.push((*id, pray));
+            }
+        }
+        for (id, pray) in last {
+            self.on_pray(id, pray);
+        }
+
+        let holder = self.hand.as_ref().map(|h| h.holder);
+        let gone = holder.is_some_and(|id| self.departed.get(&id)
+            .or_else(|| self.ghosts.get(&id))
+            .is_none_or(|c| c.is_closed()));
+        if gone {
+            self.fold();
+        }
+        self.can_advance()
     }
 
     fn events(&self) -> &Vec<(Day, CityState, Declaration)> {
         &self.events
     }
 }
+
+impl Game for Classic {
+    fn reattach(&mut self, id: &PlayerId) -> Option<OracleRef> {
+        Classic::reattach(self, id)
+    }
+
+    fn detach(&mut self, id: &PlayerId, oracle: OracleRef) -> bool {
+        Classic::detach(self, id, oracle)
+    }
+
+    fn observe(&mut self, sight: Sight) -> WatcherRef {
+        Classic::observe(self, sight)
+    }
+
+    fn save(&self) -> Result<String, Error> {
+        Classic::save(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{elections::voting::Threshold, simulation::{Bots, Simulation}, waiting::OracleRef};
+
+    use super::*;
+
+    fn table(players: PlayerId) -> (BTreeMap<PlayerId, GodWayRef>, BTreeMap<PlayerId, OracleRef>) {
+        let (mut gods, mut oracles) = (BTreeMap::new(), BTreeMap::new());
+        for id in 1..=players {
+            let (god, oracle) = connect();
+            gods.insert(id, god);
+            oracles.insert(id, oracle);
+        }
+        (gods, oracles)
+    }
+
+    fn pray(action: Act, query: Vec<PlayerId>) -> Pray {
+        Pray { action, query, meta: None }
+    }
+
+    /// Everybody puts the lowest living seat on trial and hangs them, one phase per step.
+    fn play(game: &mut Classic, oracles: &mut BTreeMap<PlayerId, OracleRef>, phases: usize) {
+        for _ in 0..phases {
+            let target = game.alive()[0];
+            for (id, oracle) in oracles.iter_mut() {
+                while oracle.read().is_some() {}
+                match game.state() {
+                    CityState::Debate => { oracle.tell(pray(Act::Nominate, vec![target])); },
+                    CityState::Hang if *id != target => { oracle.tell(pray(Act::Vote, vec![target])); },
+                    _ => {},
+                }
+            }
+            game.process();
+            game.next();
+        }
+    }
+
+    /// Sends the prays on clean channels and lets the game read them.
+    fn send(game: &mut Classic, oracles: &mut BTreeMap<PlayerId, OracleRef>, prays: Vec<(PlayerId, Pray)>) {
+        for oracle in oracles.values_mut() {
+            while oracle.read().is_some() {}
+        }
+        for (id, pray) in prays {
+            oracles.get_mut(&id).unwrap().tell(pray);
+        }
+        game.process();
+    }
+
+    /// Sends the prays, then closes the phase.
+    fn step(game: &mut Classic, oracles: &mut BTreeMap<PlayerId, OracleRef>, prays: Vec<(PlayerId, Pray)>) {
+        send(game, oracles, prays);
+        game.next();
+    }
+
+    fn verdict(game: &Classic) -> Option<Verdict> {
+        game.events().iter().rev().find_map(|(_, _, d)| match d {
+            Declaration::Verdict(verdict) => Some(verdict.clone()),
+            _ => None,
+        })
+    }
+
+    /// A table where the two defendants split the other eight votes four to four.
+    fn tied(tie: TiePolicy) -> (Classic, BTreeMap<PlayerId, OracleRef>, PlayerId, PlayerId) {
+        let voting = Voting { tie, nominate: Threshold::Plurality, hang: Threshold::Plurality };
+        let composition = Composition::recommended(10).unwrap().with_voting(voting);
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, composition, 3).unwrap();
+        let plain = game.roles().iter()
+            .filter(|(_, powers)| !powers.contains(&Power::DayShield))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let (a, b) = (plain[0], plain[1]);
+        let others = (1..=10).filter(|id| *id != a && *id != b).collect::<Vec<_>>();
+
+        let split = |action: Act| others.iter().enumerate()
+            .map(|(i, id)| (*id, pray(action, vec![if i < 4 { a } else { b }])))
+            .collect::<Vec<_>>();
+        step(&mut game, &mut oracles, split(Act::Nominate));
+        assert!(game.is_it(CityState::Defend));
+        step(&mut game, &mut oracles, vec![]);
+        step(&mut game, &mut oracles, split(Act::Vote));
+        (game, oracles, a, b)
+    }
+
+    fn draw(id: PlayerId, lot: usize) -> (PlayerId, Pray) {
+        (id, Pray { action: Act::Draw, query: vec![], meta: Some(Meta::Lot(lot)) })
+    }
+
+    fn hang(voters: &[PlayerId], on: PlayerId) -> Vec<(PlayerId, Pray)> {
+        voters.iter().map(|id| (*id, pray(Act::Vote, vec![on]))).collect()
+    }
+
+    #[test]
+    fn talks_wait_for_the_host() {
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, Composition::recommended(10).unwrap(), 1).unwrap();
+        for (id, oracle) in oracles.iter_mut() {
+            oracle.tell(pray(Act::Nominate, vec![if *id == 1 { 2 } else { 1 }]));
+        }
+        assert!(!game.process());
+        assert!(game.is_it(CityState::Debate));
+
+        game.next();
+        assert!(game.is_it(CityState::Defend));
+        assert!(!game.process());
+        assert!(!game.process());
+        assert!(game.is_it(CityState::Defend));
+
+        game.next();
+        assert!(game.is_it(CityState::Hang));
+    }
+
+    /// The vote board `id` was sent since its last read: each vote, then the closing tally.
+    fn board(oracles: &mut BTreeMap<PlayerId, OracleRef>, id: PlayerId) -> (Vec<(PlayerId, Option<PlayerId>)>, Option<Tally>) {
+        let oracle = oracles.get_mut(&id).unwrap();
+        let (mut votes, mut tally) = (Vec::new(), None);
+        while let Some(msg) = oracle.read() {
+            match msg {
+                HolyMessage::Nominated { from, on } | HolyMessage::Voted { from, on } => votes.push((from, Some(on))),
+                HolyMessage::Abstained { from, .. } => votes.push((from, None)),
+                HolyMessage::Tally(t) => tally = Some(t),
+                _ => {},
+            }
+        }
+        (votes, tally)
+    }
+
+    #[test]
+    fn votes_are_broadcast_and_tallied_at_the_close() {
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, Composition::recommended(10).unwrap(), 3).unwrap();
+        let (victim, watcher) = (1, 10);
+        let mut prays = (2..=7).map(|id| (id, pray(Act::Nominate, vec![victim]))).collect::<Vec<_>>();
+        prays.push((8, pray(Act::Nominate, vec![9])));
+        prays.push((9, pray(Act::Nominate, vec![])));
+
+        send(&mut game, &mut oracles, prays);
+        let (votes, tally) = board(&mut oracles, watcher);
+        assert_eq!(votes.len(), 8);
+        assert_eq!(votes[6..], [(8, Some(9)), (9, None)]);
+        assert!(tally.is_none());
+
+        game.next();
+        let tally = board(&mut oracles, watcher).1.unwrap();
+        assert!(tally.action == Act::Nominate);
+        assert_eq!(tally.votes[&victim].len(), 6);
+        assert_eq!(tally.abstained, BTreeSet::from([9]));
+        assert_eq!(tally.crossed, vec![victim]);
+
+        step(&mut game, &mut oracles, vec![]);
+        send(&mut game, &mut oracles, hang(&[2, 3, 4, 5, 6, 7], victim));
+        assert_eq!(board(&mut oracles, watcher).0, (2..=7).map(|id| (id, Some(victim))).collect::<Vec<_>>());
+        game.next();
+        let tally = board(&mut oracles, watcher).1.unwrap();
+        assert!(tally.action == Act::Vote);
+        assert_eq!(tally.crossed, vec![victim]);
+    }
+
+    #[test]
+    fn nobody_is_hanged_on_a_tie() {
+        let (game, _, a, b) = tied(TiePolicy::Nobody);
+        let verdict = verdict(&game).unwrap();
+        assert_eq!(verdict, Verdict { hanged: None, tied: vec![a, b], decided: Decided::Policy(TiePolicy::Nobody) });
+        assert!(game.is_it(CityState::Night));
+        assert_eq!(game.alive().len(), 10);
+    }
+
+    #[test]
+    fn a_random_defendant_is_hanged_on_a_tie() {
+        let (game, _, a, b) = tied(TiePolicy::Random);
+        let verdict = verdict(&game).unwrap();
+        assert_eq!(verdict.decided, Decided::Policy(TiePolicy::Random));
+        assert!(verdict.hanged == Some(a) || verdict.hanged == Some(b));
+        assert_eq!(game.alive().len(), 9);
+    }
+
+    #[test]
+    fn a_tie_goes_to_one_revote() {
+        let (mut game, mut oracles, a, b) = tied(TiePolicy::Revote);
+        assert!(verdict(&game).is_none());
+        assert!(game.is_it(CityState::Hang));
+        let voters = (1..=10).filter(|id| *id != a && *id != b).collect::<Vec<_>>();
+
+        step(&mut game, &mut oracles, hang(&voters[..5], a).into_iter().chain(hang(&voters[5..], b)).collect());
+        let verdict = verdict(&game).unwrap();
+        assert_eq!(verdict, Verdict { hanged: Some(a), tied: vec![], decided: Decided::Policy(TiePolicy::Revote) });
+        assert!(!game.alive().contains(&a));
+    }
+
+    #[test]
+    fn a_second_tie_hangs_nobody() {
+        let (mut game, mut oracles, a, b) = tied(TiePolicy::Revote);
+        let voters = (1..=10).filter(|id| *id != a && *id != b).collect::<Vec<_>>();
+
+        step(&mut game, &mut oracles, hang(&voters[..4], a).into_iter().chain(hang(&voters[4..], b)).collect());
+        let verdict = verdict(&game).unwrap();
+        assert_eq!(verdict, Verdict { hanged: None, tied: vec![a, b], decided: Decided::Policy(TiePolicy::Revote) });
+        assert!(game.is_it(CityState::Night));
+    }
+
+    #[test]
+    fn a_tie_goes_to_a_lot_draw() {
+        // The seed makes the second lot fatal, whoever draws it.
+        for (first, second) in [(1, 2), (2, 1)].iter().copied() {
+            let (mut game, mut oracles, a, b) = tied(TiePolicy::LotDraw);
+            assert!(game.is_it(CityState::Hang));
+            let watcher = seat_with(&game, |_| true, &[a, b]);
+
+            step(&mut game, &mut oracles, vec![draw(a, first), draw(b, second)]);
+            let drew = std::iter::from_fn(|| oracles.get_mut(&watcher).unwrap().read())
+                .filter_map(|msg| match msg {
+                    HolyMessage::Drew { from, lot } => Some((from, lot)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(drew, vec![(a, first), (b, second)]);
+
+            let verdict = verdict(&game).unwrap();
+            assert_eq!(verdict.decided, Decided::Policy(TiePolicy::LotDraw));
+            assert_eq!(verdict.tied, vec![a, b]);
+            assert_eq!(verdict.hanged, Some(if first == 2 { a } else { b }));
+            assert_eq!(game.alive().len(), 9);
+        }
+    }
+
+    #[test]
+    fn only_free_lots_in_range_can_be_drawn() {
+        let (mut game, mut oracles, a, b) = tied(TiePolicy::LotDraw);
+        send(&mut game, &mut oracles, vec![draw(a, 1)]);
+        for lot in [0, 3, 1].iter().copied() {
+            send(&mut game, &mut oracles, vec![draw(b, lot)]);
+            assert_eq!(rejections(&mut oracles, b), vec![Error::from(Rule::BadLot(lot)).to_string()]);
+        }
+        assert!(game.lots.as_ref().unwrap().pending(&b));
+    }
+
+    fn seat_with(game: &Classic, found: impl Fn(&Vec<Power>) -> bool, except: &[PlayerId]) -> PlayerId {
+        game.roles().iter()
+            .find(|(id, powers)| found(powers) && !except.contains(id))
+            .map(|(id, _)| *id)
+            .unwrap()
+    }
+
+    /// A table where a plain citizen was just hanged and holds `card`.
+    fn dealt(card: Card) -> (Classic, BTreeMap<PlayerId, OracleRef>, PlayerId) {
+        let composition = Composition::recommended(10).unwrap().with_last_move(true);
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, composition, 5).unwrap();
+        let holder = seat_with(&game, |powers| powers.is_empty(), &[]);
+        let others = (1..=10).filter(|id| *id != holder).collect::<Vec<_>>();
+
+        step(&mut game, &mut oracles, others.iter().map(|id| (*id, pray(Act::Nominate, vec![holder]))).collect());
+        step(&mut game, &mut oracles, vec![]);
+        step(&mut game, &mut oracles, others.iter().map(|id| (*id, pray(Act::Vote, vec![holder]))).collect());
+        assert!(game.is_it(CityState::Night));
+        game.hand.as_mut().unwrap().card = card;
+        (game, oracles, holder)
+    }
+
+    fn say(text: &str) -> Pray {
+        say_on(Channel::Public, text)
+    }
+
+    fn say_on(channel: Channel, text: &str) -> Pray {
+        Pray { action: Act::Talk, query: vec![], meta: Some(Meta::Say(channel, text.to_owned())) }
+    }
+
+    /// Who was heard by `id` since its last read, and on which channel.
+    fn heard(oracles: &mut BTreeMap<PlayerId, OracleRef>, id: PlayerId) -> Vec<(PlayerId, Channel)> {
+        let oracle = oracles.get_mut(&id).unwrap();
+        std::iter::from_fn(|| oracle.read()).filter_map(|msg| match msg {
+            HolyMessage::Said(from, channel, _) => Some((from, channel)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn only_the_mafia_talk_at_night() {
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, Composition::recommended(10).unwrap(), 3).unwrap();
+        let mafia = game.room.by_power(&Power::Mafia);
+        let citizen = seat_with(&game, |powers| powers.is_empty(), &[]);
+
+        send(&mut game, &mut oracles, vec![(mafia[0], say_on(Channel::Mafia, "tonight"))]);
+        assert_eq!(rejections(&mut oracles, mafia[0]), vec![Error::from(Rule::Closed(Channel::Mafia, CityState::Debate)).to_string()]);
+
+        step(&mut game, &mut oracles, vec![]);
+        assert!(game.is_it(CityState::Night));
+        send(&mut game, &mut oracles, vec![
+            (mafia[0], say_on(Channel::Mafia, "tonight")),
+            (citizen, say_on(Channel::Mafia, "let me in")),
+        ]);
+        assert_eq!(rejections(&mut oracles, citizen), vec![Error::from(Rule::MafiaOnly(citizen)).to_string()]);
+        for id in (1..=10).filter(|id| *id != citizen) {
+            let expected = if mafia.contains(&id) { vec![(mafia[0], Channel::Mafia)] } else { vec![] };
+            assert_eq!(heard(&mut oracles, id), expected);
+        }
+    }
+
+    #[test]
+    fn last_words_are_for_the_departed_only_once() {
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, Composition::recommended(10).unwrap(), 3).unwrap();
+        let victim = seat_with(&game, |powers| powers.is_empty(), &[]);
+        let others = (1..=10).filter(|id| *id != victim).collect::<Vec<_>>();
+
+        send(&mut game, &mut oracles, vec![(others[0], say_on(Channel::LastWords, "not yet"))]);
+        assert_eq!(rejections(&mut oracles, others[0]), vec![Error::from(Rule::NoLastWords(others[0])).to_string()]);
+
+        step(&mut game, &mut oracles, others.iter().map(|id| (*id, pray(Act::Nominate, vec![victim]))).collect());
+        step(&mut game, &mut oracles, vec![]);
+        step(&mut game, &mut oracles, hang(&others, victim));
+        send(&mut game, &mut oracles, vec![(victim, say_on(Channel::LastWords, "farewell"))]);
+        assert_eq!(heard(&mut oracles, others[0]), vec![(victim, Channel::LastWords)]);
+
+        send(&mut game, &mut oracles, vec![(victim, say_on(Channel::LastWords, "one more"))]);
+        assert!(heard(&mut oracles, others[0]).is_empty());
+        assert_eq!(rejections(&mut oracles, victim), vec![Error::from(Rule::NoLastWords(victim)).to_string()]);
+    }
+
+    #[test]
+    fn silence_of_the_lambs_mutes_the_next_day() {
+        let (mut game, mut oracles, holder) = dealt(Card::SilenceOfTheLambs);
+        let first = seat_with(&game, |_| true, &[holder]);
+        let second = seat_with(&game, |_| true, &[holder, first]);
+        game.on_card(holder, pray(Act::LastMove, vec![first, second])).unwrap();
+        assert!(game.hand.is_none());
+
+        step(&mut game, &mut oracles, vec![]);
+        assert!(game.is_it(CityState::Debate));
+        assert_eq!(game.on_talk(first, say("hello")), Err(Rule::Silenced(first).into()));
+        assert_eq!(game.on_talk(second, say("hello")), Err(Rule::Silenced(second).into()));
+        let free = seat_with(&game, |_| true, &[holder, first, second]);
+        assert_eq!(game.on_talk(free, say("hello")), Ok(()));
+    }
+
+    #[test]
+    fn handcuffs_cancel_the_night_action() {
+        let (mut game, mut oracles, holder) = dealt(Card::Handcuffs);
+        let boss = seat_with(&game, |powers| powers.contains(&Power::NightKill), &[]);
+        let victim = seat_with(&game, |powers| powers.is_empty(), &[holder]);
+        game.on_card(holder, pray(Act::LastMove, vec![boss])).unwrap();
+
+        step(&mut game, &mut oracles, vec![(boss, pray(Act::Use(Power::NightKill), vec![victim]))]);
+        assert!(game.alive().contains(&victim));
+        assert!(game.handcuffed.is_empty());
+    }
+
+    #[test]
+    fn face_off_swaps_roles_and_passes_the_gun() {
+        let (mut game, _, holder) = dealt(Card::FaceOff);
+        let boss = seat_with(&game, |powers| powers.contains(&Power::NightKill), &[]);
+        game.on_card(holder, pray(Act::LastMove, vec![boss])).unwrap();
+
+        assert_eq!(game.room.role(&boss), Some("Citizen"));
+        assert!(game.room.kinks(&boss).is_empty());
+        assert_eq!(game.room.count(&Power::NightKill), 1);
+    }
+
+    #[test]
+    fn face_off_takes_the_targets_role_to_the_grave() {
+        let (mut game, _, holder) = dealt(Card::FaceOff);
+        let boss = seat_with(&game, |powers| powers.contains(&Power::NightKill), &[]);
+        let (role, powers) = (game.room.role(&boss).map(str::to_owned), game.room.kinks(&boss));
+        game.on_card(holder, pray(Act::LastMove, vec![boss])).unwrap();
+
+        assert_eq!(game.graveyard.get(&holder), Some(&(role, powers)));
+        assert_eq!(game.faction(&holder), Faction::Mafia);
+        assert_eq!(game.faction(&boss), Faction::City);
+    }
+
+    #[test]
+    fn an_unplayed_card_is_forfeited() {
+        let (mut game, mut oracles, holder) = dealt(Card::Handcuffs);
+        step(&mut game, &mut oracles, vec![]);
+        assert!(game.hand.is_none());
+        assert!(game.events().iter().any(|(_, _, d)| matches!(d, Declaration::CardPlayed(id, Card::Handcuffs, on) if *id == holder && on.is_empty())));
+    }
+
+    #[test]
+    fn beautiful_mind_revives_whoever_finds_the_killer() {
+        let (mut game, _, holder) = dealt(Card::BeautifulMind);
+        let boss = seat_with(&game, |powers| powers.contains(&Power::NightKill), &[]);
+        game.on_card(holder, pray(Act::LastMove, vec![boss])).unwrap();
+
+        assert!(game.alive().contains(&holder));
+        assert!(game.room.kinks(&holder).is_empty());
+        assert!(!game.hanged.contains(&holder));
+        assert!(matches!(game.events().last(), Some((_, _, Declaration::Back(id))) if *id == holder));
+    }
+
+    #[test]
+    fn beautiful_mind_misses_an_innocent() {
+        let (mut game, _, holder) = dealt(Card::BeautifulMind);
+        let innocent = seat_with(&game, |powers| powers.is_empty(), &[holder]);
+        game.on_card(holder, pray(Act::LastMove, vec![innocent])).unwrap();
+
+        assert!(!game.alive().contains(&holder));
+        assert!(game.hand.is_none());
+    }
+
+    #[test]
+    fn only_the_holder_plays_and_may_pass() {
+        let (mut game, _, holder) = dealt(Card::Handcuffs);
+        let other = seat_with(&game, |_| true, &[holder]);
+        assert_eq!(game.on_card(other, pray(Act::LastMove, vec![holder])), Err(Rule::NoCard(other).into()));
+        assert_eq!(game.on_card(holder, pray(Act::LastMove, vec![holder])), Err(Lookup::Player(holder).into()));
+
+        game.on_card(holder, pray(Act::LastMove, vec![])).unwrap();
+        assert!(game.hand.is_none());
+        assert!(game.handcuffed.is_empty());
+        assert!(matches!(game.events().last(), Some((_, _, Declaration::CardPlayed(id, Card::Handcuffs, on))) if *id == holder && on.is_empty()));
+    }
+
+    #[test]
+    fn a_departed_holder_folds_the_card() {
+        let (mut game, mut oracles, holder) = dealt(Card::FaceOff);
+        assert!(!game.process());
+        oracles.remove(&holder);
+        game.process();
+        assert!(game.hand.is_none());
+    }
+
+    /// The reasons sent back to `id` since its last read.
+    fn rejections(oracles: &mut BTreeMap<PlayerId, OracleRef>, id: PlayerId) -> Vec<String> {
+        let oracle = oracles.get_mut(&id).unwrap();
+        std::iter::from_fn(|| oracle.read()).filter_map(|msg| match msg {
+            HolyMessage::Rejected { reason } => Some(reason),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn rejected_prays_tell_the_player_why() {
+        let (gods, mut oracles) = table(10);
+        let mut game = Classic::new(gods, Composition::recommended(10).unwrap(), 3).unwrap();
+        let doctor = seat_with(&game, |powers| powers.contains(&Power::Heal), &[]);
+        let citizen = seat_with(&game, |powers| powers.is_empty(), &[]);
+        let victim = seat_with(&game, |powers| powers.is_empty(), &[citizen]);
+        let others = (1..=10).filter(|id| *id != victim).collect::<Vec<_>>();
+
+        send(&mut game, &mut oracles, vec![
+            (doctor, pray(Act::Use(Power::Heal), vec![citizen])),
+            (citizen, pray(Act::Nominate, vec![citizen])),
+        ]);
+        assert_eq!(rejections(&mut oracles, doctor), vec![Error::Phase(Act::Use(Power::Heal), CityState::Debate).to_string()]);
+        assert_eq!(rejections(&mut oracles, citizen), vec![Error::from(Rule::SelfTarget(Act::Nominate)).to_string()]);
+
+        step(&mut game, &mut oracles, others.iter().map(|id| (*id, pray(Act::Nominate, vec![victim]))).collect());
+        step(&mut game, &mut oracles, vec![]);
+        step(&mut game, &mut oracles, hang(&others, victim));
+        assert!(game.is_it(CityState::Night));
+
+        send(&mut game, &mut oracles, vec![
+            (doctor, pray(Act::Use(Power::Heal), vec![victim])),
+            (citizen, pray(Act::Use(Power::Heal), vec![doctor, victim, citizen])),
+        ]);
+        assert_eq!(rejections(&mut oracles, doctor), vec![Error::from(Lookup::Player(victim)).to_string()]);
+        assert_eq!(rejections(&mut oracles, citizen), vec![Error::from(Rule::Missing(citizen, Power::Heal)).to_string()]);
+    }
+
+    #[test]
+    fn the_doctor_heals_one_player_at_a_small_table() {
+        for (players, rejected) in [(7, 1), (8, 0)].iter().copied() {
+            let (gods, mut oracles) = table(players);
+            let mut game = Classic::new(gods, Composition::recommended(players as usize).unwrap(), 3).unwrap();
+            let doctor = seat_with(&game, |powers| powers.contains(&Power::Heal), &[]);
+            let patients = (1..=players).filter(|id| *id != doctor).take(2).collect::<Vec<_>>();
+            step(&mut game, &mut oracles, vec![]);
+            assert!(game.is_it(CityState::Night));
+
+            send(&mut game, &mut oracles, vec![(doctor, pray(Act::Use(Power::Heal), patients))]);
+            assert_eq!(rejections(&mut oracles, doctor).len(), rejected);
+        }
+    }
+
+    fn events(game: &Classic) -> String {
+        serde_json::to_string(game.events()).unwrap()
+    }
+
+    #[test]
+    fn same_seed_and_prays_give_same_events() {
+        let composition = Composition::recommended(10).unwrap();
+        let (gods, mut first_oracles) = table(10);
+        let mut first = Classic::new(gods, composition.clone(), 42).unwrap();
+        let (gods, mut second_oracles) = table(10);
+        let mut second = Classic::new(gods, composition, 42).unwrap();
+
+        play(&mut first, &mut first_oracles, 8);
+        play(&mut second, &mut second_oracles, 8);
+        assert!(first.alive().len() < 10);
+        assert_eq!(events(&first), events(&second));
+        assert_eq!(first.roles(), second.roles());
+    }
+
+    #[test]
+    fn restored_game_continues_like_the_original() {
+        let composition = Composition::recommended(10).unwrap().with_last_move(true);
+        let (gods, mut oracles) = table(10);
+        let mut original = Classic::new(gods, composition, 7).unwrap();
+        play(&mut original, &mut oracles, 3);
+
+        let mut restored = Classic::restore(&original.save().unwrap()).unwrap();
+        let mut reattached = oracles.keys()
+            .filter_map(|id| restored.reattach(id).map(|oracle| (*id, oracle)))
+            .collect();
+        play(&mut original, &mut oracles, 5);
+        play(&mut restored, &mut reattached, 5);
+        assert!(original.alive().len() < restored.roles().len() - 1);
+        assert_eq!(events(&original), events(&restored));
+    }
+
+    #[test]
+    fn simulated_games_replay_from_their_seed() {
+        let simulation = Simulation::new(Composition::recommended(10).unwrap(), Bots::Mixed);
+        for seed in 0..5 {
+            let first = simulation.play(seed).unwrap();
+            let second = simulation.play(seed).unwrap();
+            assert_eq!(events(&first), events(&second));
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod composition;
 pub mod game;
-mod play;
\ No newline at end of file
+pub mod play;
+pub mod roles;
@@ -0,0 +1,139 @@
+use crate::{night::*, roles::*, scenario::*};
+
+pub struct Boss;
+pub struct Paralyzer;
+pub struct Wicked;
+pub struct Mafioso;
+pub struct Detective;
+pub struct Doctor;
+pub struct Gunsmith;
+pub struct Guardian;
+pub struct Commando;
+pub struct Citizen;
+pub struct SerialKiller;
+pub struct Jester;
+pub struct Survivor;
+
+pub const STAB: Power = Power::Custom(Tag::new("Stab"));
+
+impl Role for Boss {
+    fn name(&self) -> &str { "Boss" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn passive(&self) -> Vec<Power> { vec![Power::DodgeCommando, Power::Disguise] }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::NightKill, Effect::Kill(Kill::Shot), Targeting::one(), 4)]
+    }
+}
+
+impl Role for Paralyzer {
+    fn name(&self) -> &str { "Paralyzer" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Paralyze, Effect::Block, Targeting::one(), 1)]
+    }
+}
+
+impl Role for Wicked {
+    fn name(&self) -> &str { "Wicked" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Reveal, Effect::Kill(Kill::Guess), Targeting::one(), 3).once()]
+    }
+}
+
+impl Role for Mafioso {
+    fn name(&self) -> &str { "Mafia" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn active(&self) -> Vec<Ability> { vec![] }
+}
+
+impl Role for Detective {
+    fn name(&self) -> &str { "Detective" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Enquery, Effect::Investigate, Targeting::one(), 7)]
+    }
+}
+
+impl Role for Doctor {
+    fn name(&self) -> &str { "Doctor" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Heal, Effect::Protect(Against::Kill), Targeting::up_to(2).with_self().crowd(8), 2)]
+    }
+}
+
+impl Role for Gunsmith {
+    fn name(&self) -> &str { "Gunsmith" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![
+            Ability::new(Power::HandFakeGun, Effect::GiveItem { first: Item::FakeGun, rest: Item::FakeGun }, Targeting::up_to(2), 6),
+            Ability::new(Power::HandGun, Effect::GiveItem { first: Item::Gun, rest: Item::FakeGun }, Targeting::up_to(2), 6),
+        ]
+    }
+}
+
+impl Role for Guardian {
+    fn name(&self) -> &str { "Guardian" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn passive(&self) -> Vec<Power> { vec![Power::DayShield] }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Guard, Effect::Protect(Against::Block), Targeting::one().with_self(), 0)]
+    }
+}
+
+impl Role for Commando {
+    fn name(&self) -> &str { "Commando" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::ShotOnKill, Effect::Kill(Kill::Counter), Targeting::one(), 5).optional()]
+    }
+}
+
+impl Role for Citizen {
+    fn name(&self) -> &str { "Citizen" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> { vec![] }
+}
+
+impl Role for SerialKiller {
+    fn name(&self) -> &str { "SerialKiller" }
+    fn faction(&self) -> Faction { Faction::SerialKiller }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(STAB, Effect::Kill(Kill::Shot), Targeting::one(), 4)]
+    }
+}
+
+impl Role for Jester {
+    fn name(&self) -> &str { "Jester" }
+    fn faction(&self) -> Faction { Faction::Jester }
+    fn active(&self) -> Vec<Ability> { vec![] }
+}
+
+impl Role for Survivor {
+    fn name(&self) -> &str { "Survivor" }
+    fn faction(&self) -> Faction { Faction::Survivor }
+    fn active(&self) -> Vec<Ability> { vec![] }
+}
+
+fn build() -> Result<Registry, Error> {
+    Registry::new()
+        .with(Boss)?
+        .with(Paralyzer)?
+        .with(Wicked)?
+        .with(Mafioso)?
+        .with(Detective)?
+        .with(Doctor)?
+        .with(Gunsmith)?
+        .with(Guardian)?
+        .with(Commando)?
+        .with(Citizen)?
+        .with(SerialKiller)?
+        .with(Jester)?
+        .with(Survivor)
+}
+
+pub fn registry() -> Registry {
+    build().expect("Classic roles are registered once")
+}
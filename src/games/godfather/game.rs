@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use crate::{games::{classic::{composition::{Bundle, Composition, Options}, game::Classic}, registry::{Description, Entry, Game}}, observer::{Sight, WatcherRef}, scenario::*, waiting::{GodWayRef, OracleRef, PlayerId}};
+
+use super::roles;
+
+pub struct Godfather {
+    game: Classic,
+}
+
+impl Godfather {
+    pub const NAME: &'static str = "godfather";
+
+    pub fn composition(players: usize) -> Result<Composition, Error> {
+        if !(Composition::MIN_PLAYERS..=Composition::MAX_PLAYERS).contains(&players) {
+            return Err(Config::Players {
+                name: "Godfather".to_owned(),
+                min: Composition::MIN_PLAYERS,
+                max: Composition::MAX_PLAYERS,
+                got: players,
+            }.into())
+        }
+
+        let mafia = players / 3;
+        let city = players - mafia;
+
+        let mut mafia_roles = vec!["Godfather", "SaulGoodman", "Matador"];
+        mafia_roles.truncate(mafia);
+        let mut city_roles = vec!["DoctorWatson", "Detective", "Leon", "CitizenKane", "Constantine", "Nostradamus"];
+        city_roles.truncate(city - 2);
+
+        let mut bundles = Vec::new();
+        let plain_mafia = mafia - mafia_roles.len();
+        let plain_city = city - city_roles.len();
+        bundles.extend(mafia_roles.into_iter().map(|r| Bundle::role(r, 1)));
+        if plain_mafia > 0 {
+            bundles.push(Bundle::role("Mafia", plain_mafia));
+        }
+        bundles.extend(city_roles.into_iter().map(|r| Bundle::role(r, 1)));
+        bundles.push(Bundle::role("Citizen", plain_city));
+
+        Ok(Composition::new(bundles).with_last_move(true))
+    }
+
+    pub fn new(players: BTreeMap<PlayerId, GodWayRef>, seed: u64) -> Result<Self, Error> {
+        let composition = Self::composition(players.len())?;
+        Self::with_composition(players, composition, seed)
+    }
+
+    pub fn with_composition(players: BTreeMap<PlayerId, GodWayRef>, composition: Composition, seed: u64) -> Result<Self, Error> {
+        let game = Classic::with_registry(players, composition, seed, Self::NAME, roles::registry())?;
+        Ok(Self { game })
+    }
+
+    pub fn save(&self) -> Result<String, Error> {
+        self.game.save()
+    }
+
+    pub fn restore(json: &str) -> Result<Self, Error> {
+        let game = Classic::restore_with(json, Self::NAME, roles::registry())?;
+        Ok(Self { game })
+    }
+
+    pub fn entry() -> Entry {
+        let defaults = Options { last_move: Some(true), ..Options::default() };
+        Entry {
+            description: Description {
+                name: Self::NAME.to_owned(),
+                players: Composition::MIN_PLAYERS..=Composition::MAX_PLAYERS,
+                roles: roles::registry().roles().map(|r| r.name().to_owned()).collect(),
+                options: serde_json::to_value(defaults).unwrap_or_default(),
+            },
+            validate: |description, options| description.options::<Options>(options).map(|_| ()),
+            build: |description, players, options, seed| {
+                let options: Options = description.options(options)?;
+                let composition = options.apply(Godfather::composition(players.len())?);
+                Ok(Box::new(Godfather::with_composition(players, composition, seed)?))
+            },
+            restore: |json| Ok(Box::new(Godfather::restore(json)?)),
+        }
+    }
+
+    pub fn reattach(&mut self, id: &PlayerId) -> Option<OracleRef> {
+        self.game.reattach(id)
+    }
+
+    pub fn detach(&mut self, id: &PlayerId, oracle: OracleRef) -> bool {
+        self.game.detach(id, oracle)
+    }
+
+    pub fn observe(&mut self, sight: Sight) -> WatcherRef {
+        self.game.observe(sight)
+    }
+
+    pub fn game(&self) -> &Classic {
+        &self.game
+    }
+}
+
+impl Scenario for Godfather {
+    fn state(&self) -> CityState {
+        self.game.state()
+    }
+
+    fn day(&self) -> Day {
+        self.game.day()
+    }
+
+    fn next(&mut self) -> CityState {
+        self.game.next()
+    }
+
+    fn events(&self) -> &Vec<(Day, CityState, Declaration)> {
+        self.game.events()
+    }
+
+    fn process(&mut self) -> bool {
+        self.game.process()
+    }
+}
+
+impl Game for Godfather {
+    fn reattach(&mut self, id: &PlayerId) -> Option<OracleRef> {
+        Godfather::reattach(self, id)
+    }
+
+    fn detach(&mut self, id: &PlayerId, oracle: OracleRef) -> bool {
+        Godfather::detach(self, id, oracle)
+    }
+
+    fn observe(&mut self, sight: Sight) -> WatcherRef {
+        Godfather::observe(self, sight)
+    }
+
+    fn save(&self) -> Result<String, Error> {
+        Godfather::save(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::waiting::connect;
+
+    use super::{roles::SELF_HEAL, *};
+
+    #[test]
+    fn watson_heals_himself_only_once() {
+        let (mut gods, mut oracles) = (BTreeMap::new(), BTreeMap::new());
+        for id in 1..=10 {
+            let (god, oracle) = connect();
+            gods.insert(id, god);
+            oracles.insert(id, oracle);
+        }
+        let mut game = Godfather::new(gods, 7).unwrap();
+        let watson = game.game().roles().iter()
+            .find(|(_, powers)| powers.contains(&SELF_HEAL))
+            .map(|(id, _)| *id)
+            .unwrap();
+        let oracle = oracles.get_mut(&watson).unwrap();
+        let heal = Pray { action: Act::Use(Power::Heal), query: vec![watson], meta: None };
+
+        let mut rejected = Vec::new();
+        for _ in 0..2 {
+            game.next();
+            assert_eq!(game.state(), CityState::Night);
+            oracle.tell(heal.clone());
+            game.process();
+            game.next();
+            rejected.extend(std::iter::from_fn(|| oracle.read()).filter(|msg| matches!(msg, HolyMessage::Rejected { .. })));
+        }
+        assert_eq!(rejected.len(), 1);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{games::classic::roles::{Citizen, Detective, Mafioso}, night::*, roles::*, scenario::*};
+
+pub struct Godfather;
+pub struct SaulGoodman;
+pub struct Matador;
+pub struct Nostradamus;
+pub struct DoctorWatson;
+pub struct Leon;
+pub struct CitizenKane;
+pub struct Constantine;
+
+pub const SIXTH_SENSE: Power = Power::Custom(Tag::new("SixthSense"));
+pub const BUY: Power = Power::Custom(Tag::new("Buy"));
+pub const FORESEE: Power = Power::Custom(Tag::new("Foresee"));
+pub const SNIPE: Power = Power::Custom(Tag::new("Snipe"));
+pub const EXPOSE: Power = Power::Custom(Tag::new("Expose"));
+pub const REVIVE: Power = Power::Custom(Tag::new("Revive"));
+pub const SELF_HEAL: Power = Power::Custom(Tag::new("SelfHeal"));
+
+impl Role for Godfather {
+    fn name(&self) -> &str { "Godfather" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn passive(&self) -> Vec<Power> { vec![Power::Disguise, Power::Vest] }
+    fn active(&self) -> Vec<Ability> {
+        vec![
+            Ability::new(Power::NightKill, Effect::Kill(Kill::Shot), Targeting::one(), 4),
+            Ability::new(SIXTH_SENSE, Effect::Kill(Kill::Sense), Targeting::one(), 3).optional(),
+        ]
+    }
+}
+
+impl Role for SaulGoodman {
+    fn name(&self) -> &str { "SaulGoodman" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(BUY, Effect::Recruit, Targeting::one(), 3).once()]
+    }
+}
+
+impl Role for Matador {
+    fn name(&self) -> &str { "Matador" }
+    fn faction(&self) -> Faction { Faction::Mafia }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Paralyze, Effect::Block, Targeting::one(), 1)]
+    }
+}
+
+impl Role for Nostradamus {
+    fn name(&self) -> &str { "Nostradamus" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(FORESEE, Effect::Count, Targeting::exactly(3), 7).once()]
+    }
+}
+
+impl Role for DoctorWatson {
+    fn name(&self) -> &str { "DoctorWatson" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn passive(&self) -> Vec<Power> { vec![SELF_HEAL] }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(Power::Heal, Effect::Protect(Against::Kill), Targeting::one().with_self_once(SELF_HEAL), 2)]
+    }
+}
+
+impl Role for Leon {
+    fn name(&self) -> &str { "Leon" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn passive(&self) -> Vec<Power> { vec![Power::Vest] }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(SNIPE, Effect::Kill(Kill::Snipe), Targeting::one(), 5).optional()]
+    }
+}
+
+impl Role for CitizenKane {
+    fn name(&self) -> &str { "CitizenKane" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(EXPOSE, Effect::Expose, Targeting::one(), 7).once()]
+    }
+}
+
+impl Role for Constantine {
+    fn name(&self) -> &str { "Constantine" }
+    fn faction(&self) -> Faction { Faction::City }
+    fn active(&self) -> Vec<Ability> {
+        vec![Ability::new(REVIVE, Effect::Revive, Targeting::dead(), 2).once()]
+    }
+}
+
+fn build() -> Result<Registry, Error> {
+    Registry::new()
+        .with(Godfather)?
+        .with(SaulGoodman)?
+        .with(Matador)?
+        .with(Mafioso)?
+        .with(Nostradamus)?
+        .with(DoctorWatson)?
+        .with(Leon)?
+        .with(CitizenKane)?
+        .with(Constantine)?
+        .with(Detective)?
+        .with(Citizen)
+}
+
+pub fn registry() -> Registry {
+    build().expect("Godfather roles are registered once")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{in_memory_room::InMemoryRoom, one_to_one_spells::OneToOneSpells, waiting::PlayerId};
+
+    use super::*;
+
+    const TABLE: [&str; 11] = ["Godfather", "SaulGoodman", "Matador", "Mafia", "Nostradamus", "DoctorWatson", "Leon", "CitizenKane", "Constantine", "Detective", "Citizen"];
+
+    fn night(casts: &[(PlayerId, Power, &[PlayerId], Option<Meta>)]) -> Outcome {
+        let registry = registry();
+        let mut room = InMemoryRoom::new(BTreeMap::new());
+        for (id, name) in (1..).zip(TABLE.iter()) {
+            let role = registry.role(name).unwrap();
+            room.seat(id, Some(role.name().to_owned()), role.powers(), None);
+        }
+        let mut spells = OneToOneSpells::new();
+        for (from, power, query, meta) in casts {
+            spells.cast(*from, Pray { action: Act::Use(*power), query: query.to_vec(), meta: meta.clone() });
+        }
+        resolve(&room, &registry, &spells)
+    }
+
+    fn killed(outcome: &Outcome) -> Vec<PlayerId> {
+        outcome.killed.iter().cloned().collect()
+    }
+
+    #[test]
+    fn saul_buys_a_plain_citizen_instead_of_shooting() {
+        let outcome = night(&[(2, BUY, &[11], None), (1, Power::NightKill, &[5], None)]);
+        assert_eq!(outcome.recruited, vec![(11, Faction::Mafia)]);
+        assert!(outcome.killed.is_empty());
+        assert!(outcome.spent.contains(&(2, BUY)));
+
+        let outcome = night(&[(2, BUY, &[10], None)]);
+        assert!(outcome.recruited.is_empty());
+    }
+
+    #[test]
+    fn nostradamus_counts_the_undisguised_mafia() {
+        let outcome = night(&[(5, FORESEE, &[1, 2, 11], None)]);
+        assert!(matches!(outcome.messages.get(&5), Some(HolyMessage::MafiaAmong(on, 1)) if *on == vec![1, 2, 11]));
+    }
+
+    #[test]
+    fn kane_exposes_only_the_mafia() {
+        assert_eq!(night(&[(8, EXPOSE, &[3], None)]).exposed, vec![3]);
+        let outcome = night(&[(8, EXPOSE, &[10], None)]);
+        assert!(outcome.exposed.is_empty());
+        assert!(outcome.spent.contains(&(8, EXPOSE)));
+    }
+
+    #[test]
+    fn constantine_revives_from_the_graveyard() {
+        let outcome = night(&[(9, REVIVE, &[12], None)]);
+        assert_eq!(outcome.revived, vec![12]);
+        assert!(outcome.spent.contains(&(9, REVIVE)));
+    }
+
+    #[test]
+    fn leon_snipes_the_mafia_and_dies_for_a_citizen() {
+        assert_eq!(killed(&night(&[(7, SNIPE, &[2], None)])), vec![2]);
+        assert_eq!(killed(&night(&[(7, SNIPE, &[10], None)])), vec![7]);
+    }
+
+    #[test]
+    fn the_sixth_sense_kills_a_named_role_through_a_heal() {
+        let sense = |role: &str| Some(Meta::Role(role.to_owned()));
+        let outcome = night(&[(1, SIXTH_SENSE, &[5], sense("Nostradamus")), (6, Power::Heal, &[5], None), (1, Power::NightKill, &[10], None)]);
+        assert_eq!(killed(&outcome), vec![5]);
+        assert_eq!(outcome.events, vec![Event::Guessed { from: 1, hit: true }]);
+
+        let outcome = night(&[(1, SIXTH_SENSE, &[5], sense("Leon"))]);
+        assert!(outcome.killed.is_empty());
+        assert_eq!(outcome.events, vec![Event::Guessed { from: 1, hit: false }]);
+    }
+
+    #[test]
+    fn a_vest_stops_one_shot() {
+        let outcome = night(&[(1, Power::NightKill, &[7], None)]);
+        assert!(outcome.killed.is_empty());
+        assert_eq!(outcome.events, vec![Event::Vested(7)]);
+        assert!(outcome.spent.contains(&(7, Power::Vest)));
+
+        let outcome = night(&[(7, SNIPE, &[1], None)]);
+        assert!(outcome.killed.is_empty());
+        assert_eq!(outcome.events, vec![Event::Vested(1)]);
+    }
+
+    #[test]
+    fn watson_spends_his_self_heal() {
+        let outcome = night(&[(6, Power::Heal, &[6], None)]);
+        assert!(outcome.spent.contains(&(6, SELF_HEAL)));
+        let outcome = night(&[(6, Power::Heal, &[10], None)]);
+        assert!(outcome.spent.is_empty());
+    }
+}
@@ -1 +1,8 @@
-pub mod classic;
\ No newline at end of file
+pub mod classic;
+pub mod godfather;
+pub mod registry;
+
+use self::{classic::game::Classic, godfather::game::Godfather, registry::Entry};
+
+/// The registry entry of every game module, registered by `Scenarios::default()`.
+pub const ENTRIES: &[fn() -> Entry] = &[Classic::entry, Godfather::entry];
@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{observer::{Sight, WatcherRef}, scenario::*, waiting::{GodWayRef, OracleRef, PlayerId}};
+
+use super::classic::game::Classic;
+
+pub trait Game: Scenario + Send {
+    fn reattach(&mut self, id: &PlayerId) -> Option<OracleRef>;
+    fn detach(&mut self, id: &PlayerId, oracle: OracleRef) -> bool;
+    fn observe(&mut self, sight: Sight) -> WatcherRef;
+    fn save(&self) -> Result<String, Error>;
+}
+
+impl Scenario for Box<dyn Game> {
+    fn state(&self) -> CityState {
+        (**self).state()
+    }
+
+    fn day(&self) -> Day {
+        (**self).day()
+    }
+
+    fn next(&mut self) -> CityState {
+        (**self).next()
+    }
+
+    fn events(&self) -> &Vec<(Day, CityState, Declaration)> {
+        (**self).events()
+    }
+
+    fn process(&mut self) -> bool {
+        (**self).process()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Description {
+    pub name: String,
+    pub players: RangeInclusive<usize>,
+    pub roles: Vec<String>,
+    /// The options a lobby may set, with their defaults.
+    pub options: Value,
+}
+
+impl Description {
+    pub fn supports(&self, players: usize) -> Result<(), Error> {
+        if !self.players.contains(&players) {
+            return Err(Config::Players {
+                name: self.name.clone(),
+                min: *self.players.start(),
+                max: *self.players.end(),
+                got: players,
+            }.into())
+        }
+        Ok(())
+    }
+
+    /// Reads `options` into the scenario's own type, `null` standing for the defaults.
+    pub fn options<T: DeserializeOwned + Default>(&self, options: &Value) -> Result<T, Error> {
+        if options.is_null() {
+            return Ok(T::default())
+        }
+        T::deserialize(options)
+            .map_err(|e| Config::Options { name: self.name.clone(), reason: e.to_string() }.into())
+    }
+}
+
+pub type Validate = fn(&Description, &Value) -> Result<(), Error>;
+pub type Build = fn(&Description, BTreeMap<PlayerId, GodWayRef>, &Value, u64) -> Result<Box<dyn Game>, Error>;
+pub type Restore = fn(&str) -> Result<Box<dyn Game>, Error>;
+
+#[derive(Clone)]
+pub struct Entry {
+    pub description: Description,
+    pub validate: Validate,
+    pub build: Build,
+    pub restore: Restore,
+}
+
+#[derive(Clone)]
+pub struct Scenarios {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Default for Scenarios {
+    fn default() -> Self {
+        let mut scenarios = Self::new();
+        for entry in super::ENTRIES {
+            scenarios.register(entry()).expect("every game module is registered once");
+        }
+        scenarios
+    }
+}
+
+impl Scenarios {
+    pub const DEFAULT: &'static str = Classic::NAME;
+
+    pub fn new() -> Self {
+        Self { entries: BTreeMap::new() }
+    }
+
+    pub fn register(&mut self, entry: Entry) -> Result<(), Error> {
+        let name = &entry.description.name;
+        if self.entries.contains_key(name) {
+            return Err(Config::DuplicateScenario(name.clone()).into())
+        }
+        self.entries.insert(name.clone(), entry);
+        Ok(())
+    }
+
+    fn entry(&self, name: &str) -> Result<&Entry, Error> {
+        self.entries.get(name)
+            .ok_or_else(|| Lookup::Scenario(name.to_owned()).into())
+    }
+
+    pub fn describe(&self, name: &str) -> Result<&Description, Error> {
+        self.entry(name).map(|e| &e.description)
+    }
+
+    pub fn list(&self) -> Vec<&Description> {
+        self.entries.values().map(|e| &e.description).collect()
+    }
+
+    pub fn check(&self, name: &str, players: usize) -> Result<(), Error> {
+        self.describe(name)?.supports(players)
+    }
+
+    pub fn validate(&self, name: &str, options: &Value) -> Result<(), Error> {
+        let entry = self.entry(name)?;
+        (entry.validate)(&entry.description, options)
+    }
+
+    pub fn build(&self, name: &str, players: BTreeMap<PlayerId, GodWayRef>, options: &Value, seed: u64) -> Result<Box<dyn Game>, Error> {
+        let entry = self.entry(name)?;
+        entry.description.supports(players.len())?;
+        (entry.validate)(&entry.description, options)?;
+        (entry.build)(&entry.description, players, options, seed)
+    }
+
+    /// Restores a save through the entry of the scenario it was saved from.
+    pub fn restore(&self, json: &str) -> Result<Box<dyn Game>, Error> {
+        let save: Value = serde_json::from_str(json)
+            .map_err(|e| Error::Serialization(format!("Unable to restore the game: {}", e)))?;
+        let name = save.get("scenario").and_then(Value::as_str).unwrap_or(Self::DEFAULT);
+        (self.entry(name)?.restore)(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{games::{classic::composition::Composition, godfather::game::Godfather}, waiting::connect};
+
+    use super::*;
+
+    fn seats(players: PlayerId) -> BTreeMap<PlayerId, GodWayRef> {
+        (1..=players).map(|id| (id, connect().0)).collect()
+    }
+
+    #[test]
+    fn unknown_scenarios_are_looked_up_in_vain() {
+        let scenarios = Scenarios::default();
+        let unknown = Some(Lookup::Scenario("poker".to_owned()).into());
+        assert_eq!(scenarios.check("poker", 10).err(), unknown);
+        assert_eq!(scenarios.build("poker", seats(10), &Value::Null, 1).err(), unknown);
+        assert_eq!(scenarios.restore(r#"{"scenario": "poker"}"#).err(), unknown);
+    }
+
+    #[test]
+    fn each_scenario_checks_its_player_count() {
+        let scenarios = Scenarios::default();
+        let (min, max) = (Composition::MIN_PLAYERS, Composition::MAX_PLAYERS);
+        for name in [Classic::NAME, Godfather::NAME].iter() {
+            assert!(scenarios.check(name, min).is_ok());
+            assert!(scenarios.check(name, max).is_ok());
+            let too_many = Config::Players { name: name.to_string(), min, max, got: max + 1 };
+            assert_eq!(scenarios.check(name, max + 1).err(), Some(too_many.into()));
+            assert!(scenarios.build(name, seats(min as PlayerId - 1), &Value::Null, 1).is_err());
+        }
+    }
+
+    #[test]
+    fn scenarios_are_registered_once_and_built_by_name() {
+        let mut scenarios = Scenarios::default();
+        assert_eq!(scenarios.register(Godfather::entry()).err(), Some(Config::DuplicateScenario(Godfather::NAME.to_owned()).into()));
+        assert_eq!(scenarios.list().len(), 2);
+
+        let game = scenarios.build(Godfather::NAME, seats(10), &Value::Null, 1).unwrap();
+        assert_eq!(game.state(), CityState::Debate);
+        let restored = scenarios.restore(&game.save().unwrap()).unwrap();
+        assert_eq!(restored.events().len(), game.events().len());
+        assert!(scenarios.validate(Classic::NAME, &serde_json::json!({ "last_move": "yes" })).is_err());
+    }
+}
@@ -1,28 +1,66 @@
 
+use crate::observer::{Observers, Sight, Watch, WatcherRef};
 use crate::room::*;
 use crate::scenario::*;
-use crate::waiting::GodWayRef;
-use crate::waiting::PlayerId;
+use crate::oracle::Oracle;
+use crate::waiting::{connect, GodWayRef, OracleRef, PlayerId};
 
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 
+/// How many messages an offline player keeps before the oldest ones are dropped.
+const MISSED: usize = 256;
 
+#[derive(Serialize, Deserialize)]
 struct Player {
+    #[serde(default)]
+    role: Option<String>,
     powers: Vec<Power>,
-    connection: GodWayRef
+    #[serde(skip, default = "Player::detached")]
+    connection: GodWayRef,
+    #[serde(skip)]
+    online: bool,
+    #[serde(default)]
+    missed: VecDeque<HolyMessage>,
 }
 
 impl Player {
     fn new(con: GodWayRef) -> Self {
         Self {
+            role: None,
             powers: Vec::new(),
-            connection: con
+            connection: con,
+            online: true,
+            missed: VecDeque::new(),
         }
     }
+
+    fn detached() -> GodWayRef {
+        connect().0
+    }
+
+    fn tell(&mut self, msg: HolyMessage) -> bool {
+        if self.online {
+            self.connection.tell(msg)
+        } else {
+            self.miss(msg);
+            true
+        }
+    }
+
+    fn miss(&mut self, msg: HolyMessage) {
+        if self.missed.len() == MISSED {
+            self.missed.pop_front();
+        }
+        self.missed.push_back(msg);
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct InMemoryRoom {
     players: BTreeMap<PlayerId, Player>,
+    #[serde(skip)]
+    observers: Observers,
 }
 
 impl InMemoryRoom {
@@ -32,35 +70,105 @@ impl InMemoryRoom {
             players.insert(k, Player::new(con));
         }
         
-        InMemoryRoom { players }
+        InMemoryRoom { players, observers: Observers::new() }
     }
 
     pub fn assign(&mut self, id: &PlayerId, powers: Vec<Power>) -> bool {
         if let Some(p) = self.players.get_mut(id) {
-            p.powers.extend(powers.into_iter());
-            p.connection.tell(HolyMessage::Assigned(p.powers.clone()));
+            p.powers.extend(powers);
+            p.tell(HolyMessage::Assigned(p.powers.clone()));
+            self.observers.tell(Watch::Assigned(*id, p.powers.clone()));
             true
         } else {
             false
         }
     }
 
-    fn text_to(&mut self, id: &PlayerId, msg: HolyMessage) -> bool {
-         self.players.get_mut(id)
-         .map(|c| c.connection.tell(msg))
-         .unwrap_or(false)
+    pub fn cast(&mut self, id: &PlayerId, role: &str) -> bool {
+        match self.players.get_mut(id) {
+            Some(p) => {
+                p.role = Some(role.to_owned());
+                true
+            },
+            None => false
+        }
     }
 
-    pub fn read_all(&mut self) -> BTreeMap<PlayerId, Vec<Pray>> {
-        let mut res = BTreeMap::new();
-        for (id, p) in self.players.iter_mut() {
-            let mut vec = Vec::new();
-            while let Some(r) = p.connection.read() {
-                vec.push(r);
-            }
-            res.insert(id.clone(), vec);
+    pub fn attach(&mut self, id: &PlayerId) -> Option<OracleRef> {
+        let p = self.players.get_mut(id)?;
+        let missed = std::mem::take(&mut p.missed);
+        let (channel, oracle) = Oracle::create(64 + missed.len(), 8);
+        p.connection = channel;
+        p.online = true;
+        p.connection.tell(HolyMessage::Assigned(p.powers.clone()));
+        for msg in missed {
+            p.connection.tell(msg);
+        }
+        Some(oracle)
+    }
+
+    pub fn detach(&mut self, id: &PlayerId, mut oracle: OracleRef) -> bool {
+        match self.players.get_mut(id) {
+            Some(p) => {
+                while let Some(msg) = oracle.read() {
+                    p.miss(msg);
+                }
+                p.connection = Player::detached();
+                p.online = false;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn observe(&mut self, sight: Sight, mut history: Vec<Watch>) -> WatcherRef {
+        let roles = self.players.iter()
+            .map(|(id, p)| Watch::Assigned(*id, p.powers.clone()));
+        history.extend(roles);
+        self.observers.join(sight, history)
+    }
+
+    pub fn watch(&mut self, w: Watch) {
+        self.observers.tell(w);
+    }
+
+    pub fn broadcast(&mut self, msg: HolyMessage) {
+        for p in self.players.values_mut() {
+            p.tell(msg.clone());
+        }
+    }
+
+    pub fn text_to(&mut self, id: &PlayerId, msg: HolyMessage) -> bool {
+        self.observers.tell(Watch::Told(*id, msg.clone()));
+        self.players.get_mut(id)
+        .map(|c| c.tell(msg))
+        .unwrap_or(false)
+    }
+
+    pub fn replace(&mut self, id: &PlayerId, role: Option<String>, powers: Vec<Power>) -> Option<(Option<String>, Vec<Power>)> {
+        let p = self.players.get_mut(id)?;
+        let old_role = std::mem::replace(&mut p.role, role);
+        let old = std::mem::replace(&mut p.powers, powers);
+        p.tell(HolyMessage::Assigned(p.powers.clone()));
+        self.observers.tell(Watch::Assigned(*id, p.powers.clone()));
+        Some((old_role, old))
+    }
+
+    pub fn seat(&mut self, id: PlayerId, role: Option<String>, powers: Vec<Power>, connection: Option<GodWayRef>) -> bool {
+        if self.players.contains_key(&id) {
+            return false
         }
-        res
+        let online = connection.is_some();
+        let mut player = Player::new(connection.unwrap_or_else(Player::detached));
+        player.online = online;
+        player.role = role;
+        player.powers = powers;
+        self.players.insert(id, player);
+        true
+    }
+
+    pub fn evict(&mut self, id: &PlayerId) -> Option<(Option<String>, Vec<Power>, GodWayRef)> {
+        self.players.remove(id).map(|p| (p.role, p.powers, p.connection))
     }
 }
 
@@ -75,7 +183,7 @@ impl Room for InMemoryRoom {
 
     fn remove(&mut self, id: &PlayerId) -> Vec<Power> {
         self.players.remove(id).map(|p| p.powers)
-        .unwrap_or(Vec::new())
+        .unwrap_or_default()
     }
 
     fn has(&self, id: &PlayerId, power: &Power) -> bool {
@@ -89,32 +197,31 @@ impl Room for InMemoryRoom {
     }
 
     fn kinks(&self, id: &PlayerId) -> Vec<Power> {
-        self.players.get(id).map(|p| p.powers.clone()).unwrap_or(Vec::new())
+        self.players.get(id).map(|p| p.powers.clone()).unwrap_or_default()
+    }
+
+    fn role(&self, id: &PlayerId) -> Option<&str> {
+        self.players.get(id).and_then(|p| p.role.as_deref())
     }
 
     fn drop_kinks<const N: usize>(&mut self, id: &PlayerId, kinks: [Power; N]) {
         if let Some(p) = self.players.get_mut(id) {
             for kink in kinks {
-                match p.powers.binary_search_by(|p| p.cmp(&kink)) {
-                    Ok(index) => { p.powers.remove(index); },
-                    Err(_) => {},
-                };
+                if let Some(index) = p.powers.iter().position(|p| *p == kink) {
+                    p.powers.remove(index);
+                }
             }
-            p.connection.tell(HolyMessage::Assigned(p.powers.clone()));
+            p.tell(HolyMessage::Assigned(p.powers.clone()));
+            self.observers.tell(Watch::Assigned(*id, p.powers.clone()));
         }
     }
 
     fn messages(&mut self, id: &PlayerId) -> Vec<Pray> {
         let mut msgs = Vec::new();
-        match self.players.get_mut(id) {
-            Some(user) => {
-                while let Some(pray) = user.connection.read() {
-                    if user.powers.contains(&pray.action) {
-                        msgs.push(pray);
-                    }
-                }
-            },
-            None => ()
+        if let Some(user) = self.players.get_mut(id) {
+            while let Some(pray) = user.connection.read() {
+                msgs.push(pray);
+            }
         };
         msgs
     }
@@ -122,10 +229,69 @@ impl Room for InMemoryRoom {
     fn by_power(&self, power: &Power) -> Vec<PlayerId> {
         self.players.iter().filter_map(|(id, p)|
             if p.powers.contains(power) {
-                Some(id.clone())
+                Some(*id)
             } else {
                 None
             }
         ).collect()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn said(n: usize) -> HolyMessage {
+        HolyMessage::Said(1, Channel::Public, n.to_string())
+    }
+
+    fn offline() -> InMemoryRoom {
+        let mut room = InMemoryRoom::new(BTreeMap::new());
+        room.seat(1, None, vec![Power::Heal], None);
+        room
+    }
+
+    fn replayed(oracle: &mut OracleRef) -> Vec<String> {
+        assert!(matches!(oracle.read(), Some(HolyMessage::Assigned(powers)) if powers == vec![Power::Heal]));
+        std::iter::from_fn(|| oracle.read()).filter_map(|msg| match msg {
+            HolyMessage::Said(_, _, text) => Some(text),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn an_offline_player_catches_up_on_attach() {
+        let mut room = offline();
+        room.broadcast(said(0));
+        room.text_to(&1, said(1));
+
+        let mut oracle = room.attach(&1).unwrap();
+        assert_eq!(replayed(&mut oracle), vec!["0", "1"]);
+        assert!(room.attach(&2).is_none());
+    }
+
+    #[test]
+    fn detaching_keeps_what_was_not_read() {
+        let mut room = offline();
+        let oracle = room.attach(&1).unwrap();
+        room.text_to(&1, said(0));
+        assert!(room.detach(&1, oracle));
+        room.text_to(&1, said(1));
+
+        let mut oracle = room.attach(&1).unwrap();
+        assert_eq!(replayed(&mut oracle), vec!["0", "1"]);
+    }
+
+    #[test]
+    fn only_the_latest_missed_messages_are_replayed() {
+        let mut room = offline();
+        for n in 0..MISSED + 10 {
+            room.text_to(&1, said(n));
+        }
+
+        let mut oracle = room.attach(&1).unwrap();
+        let replay = replayed(&mut oracle);
+        assert_eq!(replay.len(), MISSED);
+        assert_eq!(replay.first().map(String::as_str), Some("10"));
+        assert_eq!(replay.last(), Some(&(MISSED + 9).to_string()));
+    }
+}
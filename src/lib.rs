@@ -0,0 +1,22 @@
+extern crate nanoid;
+
+pub mod scenario;
+pub mod error;
+pub mod room;
+pub mod roles;
+pub mod games;
+pub mod elections;
+pub mod waiting;
+pub mod in_memory_room;
+pub mod one_to_one_spells;
+pub mod night;
+pub mod cards;
+pub mod serde_multimap;
+pub mod oracle;
+pub mod observer;
+pub mod clock;
+pub mod timer;
+pub mod lobby;
+pub mod server;
+pub mod bots;
+pub mod simulation;
@@ -0,0 +1,342 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use nanoid::nanoid;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{clock::Clock, observer::{Sight, WatcherRef}, games::registry::{Game, Scenarios}, scenario::*, timer::{Timed, Timetable}, waiting::{OracleRef, PlayerId, Token, WaitingBuilder, WaitingRoom}};
+
+const ALPHABET: [char; 32] = [
+    '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H',
+    'J', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+
+/// How long a lobby may stay empty before it is reaped.
+pub const ABANDONED: Duration = Duration::from_secs(60);
+
+pub type Code = String;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyInfo {
+    pub code: Code,
+    pub joined: usize,
+    pub limit: usize,
+    #[serde(default)]
+    pub scenario: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub code: Code,
+    pub id: PlayerId,
+    pub token: Token,
+    pub generation: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub scenario: String,
+    pub options: Value,
+}
+
+pub struct Lobbies<C: Clock + Clone> {
+    clock: C,
+    timetable: Timetable,
+    scenarios: Scenarios,
+    lobbies: BTreeMap<Code, WaitingBuilder>,
+    choices: BTreeMap<Code, Choice>,
+    touched: BTreeMap<Code, Duration>,
+    games: BTreeMap<Code, Timed<Box<dyn Game>, C>>,
+    keys: BTreeMap<Code, String>,
+    sessions: BTreeMap<Token, Session>,
+}
+
+impl<C: Clock + Clone> Lobbies<C> {
+    pub fn new(clock: C, timetable: Timetable) -> Self {
+        Self {
+            clock,
+            timetable,
+            scenarios: Scenarios::default(),
+            lobbies: BTreeMap::new(),
+            choices: BTreeMap::new(),
+            touched: BTreeMap::new(),
+            games: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    pub fn scenarios(mut self, scenarios: Scenarios) -> Self {
+        self.scenarios = scenarios;
+        self
+    }
+
+    pub fn registry(&self) -> &Scenarios {
+        &self.scenarios
+    }
+
+    fn new_code(&self) -> Code {
+        loop {
+            let code = nanoid!(6, &ALPHABET);
+            if !self.lobbies.contains_key(&code) && !self.games.contains_key(&code) {
+                return code
+            }
+        }
+    }
+
+    pub fn create(&mut self, scenario: &str, players: usize) -> Result<Code, Error> {
+        self.create_with(scenario, players, Value::Null)
+    }
+
+    pub fn create_with(&mut self, scenario: &str, players: usize, options: Value) -> Result<Code, Error> {
+        self.scenarios.check(scenario, players)?;
+        self.scenarios.validate(scenario, &options)?;
+        let code = self.new_code();
+        self.lobbies.insert(code.clone(), WaitingBuilder::new(players));
+        self.choices.insert(code.clone(), Choice { scenario: scenario.to_owned(), options });
+        self.touched.insert(code.clone(), self.clock.now());
+        self.keys.insert(code.clone(), nanoid!());
+        Ok(code)
+    }
+
+    pub fn moderator_key(&self, code: &str) -> Option<&str> {
+        self.keys.get(code).map(String::as_str)
+    }
+
+    pub fn info(&self, code: &str) -> Option<LobbyInfo> {
+        self.lobbies.get(code).map(|w| LobbyInfo {
+            code: code.to_owned(),
+            joined: w.total(),
+            limit: w.limit(),
+            scenario: self.choices.get(code).map(|c| c.scenario.clone()).unwrap_or_default(),
+        })
+    }
+
+    pub fn list(&self) -> Vec<LobbyInfo> {
+        self.lobbies.keys().filter_map(|code| self.info(code)).collect()
+    }
+
+    pub fn choice(&self, code: &str) -> Option<&Choice> {
+        self.choices.get(code)
+    }
+
+    pub fn open(&self, scenario: &str, players: usize) -> Option<Code> {
+        self.lobbies.iter()
+            .filter(|(code, _)| self.choices.get(*code).is_some_and(|c| c.scenario == scenario))
+            .find(|(_, w)| w.limit() == players && !w.ready())
+            .map(|(code, _)| code.clone())
+    }
+
+    pub fn close(&mut self, code: &str) -> bool {
+        self.keys.remove(code);
+        self.choices.remove(code);
+        self.touched.remove(code);
+        self.lobbies.remove(code).is_some()
+    }
+
+    pub fn reserve(&mut self, code: &str) -> Result<(Session, OracleRef), Error> {
+        let waiting = self.lobbies.get_mut(code)
+            .ok_or_else(|| Lookup::Lobby(code.to_owned()))?;
+        let (id, token, oracle) = waiting.reserve()
+            .ok_or_else(|| Lookup::Full(code.to_owned()))?;
+        self.touched.insert(code.to_owned(), self.clock.now());
+
+        if waiting.ready() {
+            self.start(code)?;
+        }
+        let session = Session { code: code.to_owned(), id, token, generation: 0 };
+        Ok((session, oracle))
+    }
+
+    pub fn resume(&mut self, token: &str) -> Result<(Session, OracleRef), Error> {
+        let session = self.sessions.get_mut(token)
+            .ok_or(Lookup::Session)?;
+        session.generation += 1;
+        let session = session.clone();
+
+        let oracle = self.games.get_mut(&session.code)
+            .and_then(|g| g.game_mut().reattach(&session.id))
+            .ok_or(Lookup::Player(session.id))?;
+        Ok((session, oracle))
+    }
+
+    pub fn leave(&mut self, session: &Session, oracle: OracleRef) -> bool {
+        if self.opt_out(&session.code, &session.id) {
+            return true
+        }
+
+        let current = self.sessions.get(&session.token)
+            .is_some_and(|s| s.generation == session.generation);
+        match self.games.get_mut(&session.code) {
+            Some(game) if current => game.game_mut().detach(&session.id, oracle),
+            _ => false
+        }
+    }
+
+    pub fn opt_out(&mut self, code: &str, id: &PlayerId) -> bool {
+        let left = self.lobbies.get_mut(code).is_some_and(|w| w.opt_out(id));
+        if left {
+            self.touched.insert(code.to_owned(), self.clock.now());
+        }
+        left
+    }
+
+    fn start(&mut self, code: &str) -> Result<(), Error> {
+        let waiting = self.lobbies.remove(code)
+            .ok_or_else(|| Lookup::Lobby(code.to_owned()))?;
+        let choice = self.choices.remove(code)
+            .ok_or_else(|| Lookup::Lobby(code.to_owned()))?;
+        self.touched.remove(code);
+        let tokens = waiting.tokens();
+        let room = waiting.get()
+            .ok_or(Config::Unfilled)?;
+
+        let game = self.scenarios.build(&choice.scenario, room, &choice.options, rand::random())?;
+        let game = Timed::new(game, self.clock.clone(), self.timetable);
+        self.games.insert(code.to_owned(), game);
+        for (token, id) in tokens {
+            let session = Session { code: code.to_owned(), id, token: token.clone(), generation: 0 };
+            self.sessions.insert(token, session);
+        }
+        Ok(())
+    }
+
+    pub fn games(&self) -> Vec<Code> {
+        self.games.keys().cloned().collect()
+    }
+
+    pub fn game(&self, code: &str) -> Option<&dyn Game> {
+        self.games.get(code).map(|g| g.game().as_ref())
+    }
+
+    pub fn game_mut(&mut self, code: &str) -> Option<&mut (dyn Game + 'static)> {
+        self.games.get_mut(code).map(|g| g.game_mut().as_mut())
+    }
+
+    pub fn observe(&mut self, code: &str, key: Option<&str>) -> Result<WatcherRef, Error> {
+        let sight = match key {
+            None => Sight::Spectator,
+            Some(key) if self.moderator_key(code) == Some(key) => Sight::Moderator,
+            Some(_) => return Err(Lookup::Key(code.to_owned()).into()),
+        };
+        let game = self.game_mut(code)
+            .ok_or_else(|| Lookup::Game(code.to_owned()))?;
+        Ok(game.observe(sight))
+    }
+
+    /// Closes lobbies that have been empty for longer than `ABANDONED`.
+    pub fn reap(&mut self) -> Vec<Code> {
+        let now = self.clock.now();
+        let abandoned = self.lobbies.iter()
+            .filter(|(_, w)| w.total() == 0)
+            .filter(|(code, _)| self.touched.get(*code).is_none_or(|t| now.saturating_sub(*t) >= ABANDONED))
+            .map(|(code, _)| code.clone())
+            .collect::<Vec<_>>();
+        for code in abandoned.iter() {
+            self.close(code);
+        }
+        abandoned
+    }
+
+    pub fn tick(&mut self) -> Vec<(Code, State)> {
+        let mut done = Vec::new();
+        for (code, game) in self.games.iter_mut() {
+            if game.process() {
+                game.next();
+            }
+            if let CityState::Done(state) = game.state() {
+                done.push((code.clone(), state));
+            }
+        }
+
+        for (code, _) in done.iter() {
+            self.games.remove(code);
+            self.keys.remove(code);
+            self.sessions.retain(|_, s| s.code != *code);
+        }
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{clock::ManualClock, error::Lookup};
+
+    use super::*;
+
+    fn lobbies() -> Lobbies<ManualClock> {
+        Lobbies::new(ManualClock::new(), Timetable::default())
+    }
+
+    fn filled(lobbies: &mut Lobbies<ManualClock>) -> Vec<(Session, OracleRef)> {
+        let code = lobbies.create(Scenarios::DEFAULT, 10).unwrap();
+        (0..10).map(|_| lobbies.reserve(&code).unwrap()).collect()
+    }
+
+    #[test]
+    fn a_full_lobby_becomes_a_game() {
+        let mut lobbies = lobbies();
+        let code = lobbies.create(Scenarios::DEFAULT, 10).unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(lobbies.moderator_key(&code).is_some());
+        assert_eq!(lobbies.open(Scenarios::DEFAULT, 10), Some(code.clone()));
+
+        let sessions = (0..10).map(|_| lobbies.reserve(&code).unwrap()).collect::<Vec<_>>();
+        assert!(lobbies.list().is_empty());
+        assert_eq!(lobbies.games(), vec![code.clone()]);
+        assert_eq!(lobbies.game(&code).map(|g| g.state()), Some(CityState::Debate));
+        assert_eq!(sessions.iter().map(|(s, _)| s.id).collect::<Vec<_>>(), (1..=10).collect::<Vec<_>>());
+        assert_eq!(lobbies.reserve(&code).err(), Some(Error::Lookup(Lookup::Lobby(code))));
+    }
+
+    #[test]
+    fn empty_lobbies_are_reaped_after_a_while() {
+        let clock = ManualClock::new();
+        let mut lobbies = Lobbies::new(clock.clone(), Timetable::default());
+        let empty = lobbies.create(Scenarios::DEFAULT, 10).unwrap();
+        let joined = lobbies.create(Scenarios::DEFAULT, 10).unwrap();
+        let (session, _) = lobbies.reserve(&joined).unwrap();
+
+        clock.advance(ABANDONED - Duration::from_secs(1));
+        assert!(lobbies.reap().is_empty());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lobbies.reap(), vec![empty.clone()]);
+        assert!(lobbies.info(&empty).is_none());
+
+        assert!(lobbies.opt_out(&joined, &session.id));
+        clock.advance(ABANDONED);
+        assert_eq!(lobbies.reap(), vec![joined]);
+    }
+
+    #[test]
+    fn a_closed_lobby_is_gone() {
+        let mut lobbies = lobbies();
+        let code = lobbies.create(Scenarios::DEFAULT, 10).unwrap();
+        assert!(lobbies.close(&code));
+        assert!(!lobbies.close(&code));
+        assert!(lobbies.moderator_key(&code).is_none());
+        assert!(lobbies.reserve(&code).is_err());
+    }
+
+    #[test]
+    fn a_player_resumes_with_their_token() {
+        let mut lobbies = lobbies();
+        let (session, oracle) = filled(&mut lobbies).remove(0);
+        assert!(lobbies.leave(&session, oracle));
+
+        let (resumed, mut oracle) = lobbies.resume(&session.token).unwrap();
+        assert_eq!((resumed.code, resumed.id, resumed.generation), (session.code, session.id, 1));
+        assert!(matches!(oracle.read(), Some(HolyMessage::Assigned(_))));
+        assert_eq!(lobbies.resume("nobody").err(), Some(Error::Lookup(Lookup::Session)));
+    }
+
+    #[test]
+    fn a_stale_connection_cannot_detach_a_resumed_player() {
+        let mut lobbies = lobbies();
+        let (stale, old) = filled(&mut lobbies).remove(0);
+        let (current, new) = lobbies.resume(&stale.token).unwrap();
+
+        assert!(!lobbies.leave(&stale, old));
+        assert!(lobbies.leave(&current, new));
+    }
+}
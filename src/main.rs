@@ -1,37 +1,25 @@
-use std::collections::BTreeMap;
+use std::{env, fs};
 
-use scenario::Scenario;
-
-use crate::{games::classic::game::Classic, waiting::{WaitingBuilder, WaitingRoom}};
-
-extern crate nanoid;
-mod scenario;
-mod room;
-mod games;
-mod elections;
-mod waiting;
-mod in_memory_room;
-mod oracle;
+use rmafia::{games::registry::Scenarios, server::Server, timer::Timetable};
 
 fn main() -> Result<(), String> {
-    let mut waiting_room = WaitingBuilder::new(10);
-    let mut ps = BTreeMap::new();
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
-    waiting_room.reserve().and_then(|(id, c)| ps.insert(id, c));
+    let mut args = env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:9001".to_owned());
+    let players = match args.next() {
+        Some(n) => n.parse().map_err(|_| format!("Invalid number of players: {}", n))?,
+        None => 10,
+    };
+
+    let timetable = match args.next() {
+        Some(path) => {
+            let json = fs::read_to_string(&path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+            Timetable::from_json(&json)?
+        },
+        None => Timetable::default(),
+    };
 
-    ps.get_mut(&1).unwrap().tell(todo!());
-    let room = waiting_room.get().unwrap();
-    let mut game = Classic::new(room)?;
-    let state = game.state();
-    
-    println!("Hello, world!");
-    Ok(())
+    let scenario = args.next().unwrap_or_else(|| Scenarios::DEFAULT.to_owned());
+    let server = Server::bind(addr, players)?.scenario(&scenario)?.timetable(timetable);
+    println!("Waiting for {} players of {} on ws://{}", players, scenario, server.local_addr()?);
+    server.run().map_err(String::from)
 }
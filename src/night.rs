@@ -0,0 +1,437 @@
+use std::collections::BTreeSet;
+
+use btreemultimap::BTreeMultiMap;
+
+use crate::{roles::{Faction, Registry}, room::*, scenario::*, waiting::PlayerId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Against {
+    Block,
+    Kill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kill {
+    Shot,
+    Guess,
+    Sense,
+    Counter,
+    Snipe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Gun,
+    FakeGun,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Block,
+    Protect(Against),
+    Kill(Kill),
+    Investigate,
+    GiveItem { first: Item, rest: Item },
+    Recruit,
+    Count,
+    Expose,
+    Revive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Protected(PlayerId),
+    Vested(PlayerId),
+    Guessed { from: PlayerId, hit: bool },
+    Countered { from: PlayerId, hit: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Guess {
+    Power(Power),
+    Role(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub from: PlayerId,
+    pub power: Power,
+    pub effect: Effect,
+    pub priority: u8,
+    pub once: bool,
+    pub self_cost: Option<Power>,
+    pub targets: Vec<PlayerId>,
+    pub guess: Option<Guess>,
+}
+
+impl Action {
+    fn new(registry: &Registry, (from, power, act): &RawSpell) -> Option<Self> {
+        let ability = registry.ability(power)?;
+        let (targets, guess) = match act {
+            NightAct::One(on) => (vec![*on], None),
+            NightAct::Two(first, second) => (vec![*first, *second], None),
+            NightAct::Many(ids) => (ids.clone(), None),
+            NightAct::Wicked(on, guess) => (vec![*on], Some(Guess::Power(*guess))),
+            NightAct::Sense(on, role) => (vec![*on], Some(Guess::Role(role.clone()))),
+        };
+        Some(Self {
+            from: *from,
+            power: *power,
+            effect: ability.effect,
+            priority: ability.priority,
+            once: ability.once,
+            self_cost: ability.targeting.self_cost,
+            targets,
+            guess,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Outcome {
+    pub killed: BTreeSet<PlayerId>,
+    pub messages: Messages,
+    pub items: Vec<(PlayerId, PlayerId, Item)>,
+    pub spent: Vec<(PlayerId, Power)>,
+    pub events: Vec<Event>,
+    pub recruited: Vec<(PlayerId, Faction)>,
+    pub exposed: Vec<PlayerId>,
+    pub revived: Vec<PlayerId>,
+}
+
+pub struct Resolver<'a, R: Room> {
+    room: &'a R,
+    registry: &'a Registry,
+    blocked: BTreeSet<PlayerId>,
+    protected: BTreeSet<(Against, PlayerId)>,
+    shots: BTreeSet<Faction>,
+    attacked: BTreeSet<PlayerId>,
+    handed: BTreeSet<PlayerId>,
+    outcome: Outcome,
+}
+
+impl<'a, R: Room> Resolver<'a, R> {
+    pub fn new(room: &'a R, registry: &'a Registry) -> Self {
+        Self {
+            room,
+            registry,
+            blocked: BTreeSet::new(),
+            protected: BTreeSet::new(),
+            shots: BTreeSet::new(),
+            attacked: BTreeSet::new(),
+            handed: BTreeSet::new(),
+            outcome: Outcome { messages: BTreeMultiMap::new(), ..Outcome::default() },
+        }
+    }
+
+    pub fn actions(registry: &Registry, spells: &impl Spells) -> Vec<Action> {
+        let mut actions: Vec<Action> = registry.abilities()
+            .filter_map(|ability| spells.raw_vec(&ability.power))
+            .flatten()
+            .filter_map(|spell| Action::new(registry, spell))
+            .collect();
+        actions.sort_by_key(|a| (a.priority, a.power, a.from));
+        actions
+    }
+
+    pub fn resolve(mut self, spells: &impl Spells) -> Outcome {
+        for action in Self::actions(self.registry, spells) {
+            if !self.blocked.contains(&action.from) {
+                self.apply(action);
+            }
+        }
+        self.outcome
+    }
+
+    fn faction(&self, id: &PlayerId) -> Faction {
+        self.room.role(id)
+            .and_then(|role| self.registry.faction(role))
+            .unwrap_or(Faction::City)
+    }
+
+    fn is(&self, against: Against, id: &PlayerId) -> bool {
+        self.protected.contains(&(against, *id))
+    }
+
+    fn kill(&mut self, id: PlayerId) {
+        let vested = self.room.has(&id, &Power::Vest) && !self.outcome.spent.contains(&(id, Power::Vest));
+        if self.is(Against::Kill, &id) {
+            self.outcome.events.push(Event::Protected(id));
+        } else if vested {
+            self.outcome.spent.push((id, Power::Vest));
+            self.outcome.events.push(Event::Vested(id));
+        } else {
+            self.outcome.killed.insert(id);
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        let Action { from, power, effect, once, self_cost, targets, guess, .. } = action;
+        if once {
+            self.outcome.spent.push((from, power));
+        }
+        if let Some(cost) = self_cost.filter(|_| targets.contains(&from)) {
+            self.outcome.spent.push((from, cost));
+        }
+        match effect {
+            Effect::Block => {
+                for on in targets {
+                    if !self.is(Against::Block, &on) {
+                        self.blocked.insert(on);
+                    }
+                }
+            },
+            Effect::Protect(against) => {
+                for on in targets {
+                    self.protected.insert((against, on));
+                }
+            },
+            Effect::Kill(Kill::Shot) => {
+                if self.shots.insert(self.faction(&from)) {
+                    for on in targets {
+                        self.attacked.insert(on);
+                        self.kill(on);
+                    }
+                }
+            },
+            Effect::Kill(Kill::Guess | Kill::Sense) => {
+                if !self.shots.insert(self.faction(&from)) {
+                    return
+                }
+                for on in targets {
+                    let hit = match &guess {
+                        Some(Guess::Power(power)) => self.room.has(&on, power),
+                        Some(Guess::Role(role)) => self.room.role(&on) == Some(role.as_str()),
+                        None => false,
+                    };
+                    self.outcome.events.push(Event::Guessed { from, hit });
+                    if hit {
+                        self.outcome.killed.insert(on);
+                    }
+                }
+            },
+            Effect::Kill(Kill::Counter) => {
+                if !self.attacked.contains(&from) {
+                    return
+                }
+                for on in targets {
+                    let dodged = self.room.has(&on, &Power::DodgeCommando);
+                    if !dodged && self.faction(&on) == self.faction(&from) {
+                        continue
+                    }
+                    self.outcome.spent.push((from, power));
+                    self.outcome.events.push(Event::Countered { from, hit: !dodged });
+                    self.outcome.killed.remove(&from);
+                    if !dodged {
+                        self.kill(on);
+                    }
+                }
+            },
+            Effect::Kill(Kill::Snipe) => {
+                if self.shots.insert(self.faction(&from)) {
+                    for on in targets {
+                        if self.faction(&on) == self.faction(&from) {
+                            self.outcome.killed.insert(from);
+                        } else {
+                            self.attacked.insert(on);
+                            self.kill(on);
+                        }
+                    }
+                }
+            },
+            Effect::Investigate => {
+                for on in targets {
+                    let guilty = self.faction(&on) == Faction::Mafia && !self.room.has(&on, &Power::Disguise);
+                    self.outcome.messages.insert(from, HolyMessage::IsMafia(on, guilty));
+                }
+            },
+            Effect::GiveItem { first, rest } => {
+                if !self.handed.insert(from) {
+                    return
+                }
+                for (i, on) in targets.into_iter().enumerate() {
+                    let item = if i == 0 { first } else { rest };
+                    self.outcome.items.push((from, on, item));
+                }
+            },
+            Effect::Recruit => {
+                let faction = self.faction(&from);
+                if !self.shots.insert(faction) {
+                    return
+                }
+                for on in targets {
+                    if self.room.kinks(&on).is_empty() {
+                        self.outcome.recruited.push((on, faction));
+                    }
+                }
+            },
+            Effect::Count => {
+                let guilty = targets.iter()
+                    .filter(|on| self.faction(on) == Faction::Mafia && !self.room.has(on, &Power::Disguise))
+                    .count();
+                self.outcome.messages.insert(from, HolyMessage::MafiaAmong(targets, guilty));
+            },
+            Effect::Expose => {
+                for on in targets {
+                    if self.faction(&on) == Faction::Mafia {
+                        self.outcome.exposed.push(on);
+                    }
+                }
+            },
+            Effect::Revive => {
+                self.outcome.revived.extend(targets);
+            },
+        }
+    }
+}
+
+pub fn resolve(room: &impl Room, registry: &Registry, spells: &impl Spells) -> Outcome {
+    Resolver::new(room, registry).resolve(spells)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{games::classic::roles::{self, STAB}, in_memory_room::InMemoryRoom, one_to_one_spells::OneToOneSpells};
+
+    use super::*;
+
+    const TABLE: [&str; 10] = ["Boss", "Paralyzer", "Wicked", "Detective", "Doctor", "Guardian", "Commando", "Citizen", "Gunsmith", "SerialKiller"];
+
+    fn room(registry: &Registry) -> InMemoryRoom {
+        let mut room = InMemoryRoom::new(BTreeMap::new());
+        for (id, name) in (1..).zip(TABLE.iter()) {
+            let role = registry.role(name).unwrap();
+            room.seat(id, Some(role.name().to_owned()), role.powers(), None);
+        }
+        room
+    }
+
+    fn spells(casts: &[(PlayerId, Power, &[PlayerId], Option<Meta>)]) -> OneToOneSpells {
+        let mut spells = OneToOneSpells::new();
+        for (from, power, query, meta) in casts {
+            spells.cast(*from, Pray { action: Act::Use(*power), query: query.to_vec(), meta: meta.clone() });
+        }
+        spells
+    }
+
+    fn night(casts: &[(PlayerId, Power, &[PlayerId], Option<Meta>)]) -> Outcome {
+        let registry = roles::registry();
+        resolve(&room(&registry), &registry, &spells(casts))
+    }
+
+    fn killed(outcome: &Outcome) -> Vec<PlayerId> {
+        outcome.killed.iter().cloned().collect()
+    }
+
+    #[test]
+    fn actions_resolve_by_priority() {
+        use Power::*;
+        let registry = roles::registry();
+        let casts = spells(&[
+            (4, Enquery, &[1], None),
+            (9, HandGun, &[8], None),
+            (7, ShotOnKill, &[2], None),
+            (1, NightKill, &[8], None),
+            (3, Reveal, &[5], Some(Meta::Has(Heal))),
+            (5, Heal, &[8], None),
+            (2, Paralyze, &[4], None),
+            (6, Guard, &[4], None),
+        ]);
+        let order = Resolver::<InMemoryRoom>::actions(&registry, &casts).iter().map(|a| a.power).collect::<Vec<_>>();
+        assert_eq!(order, vec![Guard, Paralyze, Heal, Reveal, NightKill, ShotOnKill, HandGun, Enquery]);
+    }
+
+    #[test]
+    fn a_blocked_player_does_nothing() {
+        let outcome = night(&[(2, Power::Paralyze, &[1], None), (1, Power::NightKill, &[8], None)]);
+        assert!(outcome.killed.is_empty());
+    }
+
+    #[test]
+    fn a_guard_protects_from_blocks() {
+        let outcome = night(&[
+            (6, Power::Guard, &[1], None),
+            (2, Power::Paralyze, &[1], None),
+            (1, Power::NightKill, &[8], None),
+        ]);
+        assert_eq!(killed(&outcome), vec![8]);
+    }
+
+    #[test]
+    fn a_heal_protects_from_kills() {
+        let outcome = night(&[(5, Power::Heal, &[8], None), (1, Power::NightKill, &[8], None)]);
+        assert!(outcome.killed.is_empty());
+        assert_eq!(outcome.events, vec![Event::Protected(8)]);
+    }
+
+    #[test]
+    fn a_blocked_doctor_heals_nobody() {
+        let outcome = night(&[
+            (2, Power::Paralyze, &[5], None),
+            (5, Power::Heal, &[8], None),
+            (1, Power::NightKill, &[8], None),
+        ]);
+        assert_eq!(killed(&outcome), vec![8]);
+    }
+
+    #[test]
+    fn an_attacked_commando_shoots_back() {
+        let outcome = night(&[(1, Power::NightKill, &[7], None), (7, Power::ShotOnKill, &[2], None)]);
+        assert_eq!(killed(&outcome), vec![2]);
+        assert_eq!(outcome.events, vec![Event::Countered { from: 7, hit: true }]);
+        assert!(outcome.spent.contains(&(7, Power::ShotOnKill)));
+    }
+
+    #[test]
+    fn the_boss_dodges_the_commando() {
+        let outcome = night(&[(1, Power::NightKill, &[7], None), (7, Power::ShotOnKill, &[1], None)]);
+        assert!(outcome.killed.is_empty());
+        assert_eq!(outcome.events, vec![Event::Countered { from: 7, hit: false }]);
+    }
+
+    #[test]
+    fn a_commando_only_counters_when_attacked() {
+        let outcome = night(&[(1, Power::NightKill, &[8], None), (7, Power::ShotOnKill, &[2], None)]);
+        assert_eq!(killed(&outcome), vec![8]);
+        assert!(outcome.events.is_empty());
+    }
+
+    #[test]
+    fn a_commando_does_not_shoot_the_city() {
+        let outcome = night(&[(1, Power::NightKill, &[7], None), (7, Power::ShotOnKill, &[4], None)]);
+        assert_eq!(killed(&outcome), vec![7]);
+        assert!(outcome.events.is_empty());
+    }
+
+    #[test]
+    fn each_faction_shoots_once() {
+        let outcome = night(&[
+            (3, Power::Reveal, &[5], Some(Meta::Has(Power::Heal))),
+            (1, Power::NightKill, &[8], None),
+            (10, STAB, &[4], None),
+        ]);
+        assert_eq!(killed(&outcome), vec![4, 5]);
+        assert_eq!(outcome.events, vec![Event::Guessed { from: 3, hit: true }]);
+        assert!(outcome.spent.contains(&(3, Power::Reveal)));
+    }
+
+    #[test]
+    fn a_wrong_guess_spends_the_shot() {
+        let outcome = night(&[
+            (3, Power::Reveal, &[5], Some(Meta::Has(Power::Enquery))),
+            (1, Power::NightKill, &[8], None),
+        ]);
+        assert!(outcome.killed.is_empty());
+        assert_eq!(outcome.events, vec![Event::Guessed { from: 3, hit: false }]);
+    }
+
+    #[test]
+    fn the_disguised_boss_looks_innocent() {
+        let outcome = night(&[(4, Power::Enquery, &[1], None)]);
+        assert!(matches!(outcome.messages.get(&4), Some(HolyMessage::IsMafia(1, false))));
+        let outcome = night(&[(4, Power::Enquery, &[2], None)]);
+        assert!(matches!(outcome.messages.get(&4), Some(HolyMessage::IsMafia(2, true))));
+    }
+}
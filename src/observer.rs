@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{oracle::{Oracle, TwoWayRing}, scenario::*, waiting::PlayerId};
+
+pub type WatcherRef = TwoWayRing<(), Watch>;
+pub type EyeWayRef = TwoWayRing<Watch, ()>;
+
+/// Room left in a watcher's ring for live updates after the history is replayed.
+const LIVE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sight {
+    Moderator,
+    Spectator,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Watch {
+    Declared(Day, CityState, Declaration),
+    Prayed(PlayerId, Pray),
+    Told(PlayerId, HolyMessage),
+    Assigned(PlayerId, Vec<Power>),
+    Said(PlayerId, Channel, String),
+    Broadcast(HolyMessage),
+    Night {
+        day: Day,
+        removed: Vec<PlayerId>,
+        messages: Vec<(PlayerId, HolyMessage)>,
+        triggers: Vec<serde_json::Value>,
+    },
+    Fault(Error),
+}
+
+impl Watch {
+    pub fn public(&self) -> bool {
+        match self {
+            Watch::Declared(_, _, d) => d.public(),
+            Watch::Said(_, channel, _) => *channel != Channel::Mafia,
+            Watch::Broadcast(_) => true,
+            _ => false
+        }
+    }
+
+    pub fn visible(&self, sight: Sight) -> bool {
+        sight == Sight::Moderator || self.public()
+    }
+}
+
+pub fn connect(capacity: usize) -> (EyeWayRef, WatcherRef) {
+    Oracle::create(capacity, 1)
+}
+
+#[derive(Default)]
+pub struct Observers {
+    eyes: Vec<(Sight, EyeWayRef)>,
+}
+
+impl Observers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn join(&mut self, sight: Sight, history: Vec<Watch>) -> WatcherRef {
+        let history = history.into_iter().filter(|w| w.visible(sight)).collect::<Vec<_>>();
+        let (mut eye, watcher) = connect(history.len() + LIVE);
+        for w in history {
+            eye.tell(w);
+        }
+        self.eyes.push((sight, eye));
+        watcher
+    }
+
+    pub fn tell(&mut self, w: Watch) {
+        self.eyes.retain(|(_, eye)| !eye.is_closed());
+        for (sight, eye) in self.eyes.iter_mut() {
+            if w.visible(*sight) {
+                eye.tell(w.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn said(channel: Channel) -> Watch {
+        Watch::Said(1, channel, "hi".to_owned())
+    }
+
+    fn seen(watcher: &mut WatcherRef) -> Vec<String> {
+        std::iter::from_fn(|| watcher.read())
+            .map(|w| serde_json::to_string(&w).unwrap())
+            .collect()
+    }
+
+    fn all() -> Vec<Watch> {
+        vec![
+            Watch::Told(1, HolyMessage::YouHaveGun),
+            Watch::Assigned(1, vec![Power::Mafia]),
+            said(Channel::Mafia),
+            said(Channel::Public),
+            Watch::Broadcast(HolyMessage::YouAreBoss),
+        ]
+    }
+
+    fn public() -> Vec<String> {
+        [said(Channel::Public), Watch::Broadcast(HolyMessage::YouAreBoss)].iter()
+            .map(|w| serde_json::to_string(w).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn spectators_miss_private_messages_and_mafia_chat() {
+        let mut observers = Observers::new();
+        let mut spectator = observers.join(Sight::Spectator, vec![]);
+        let mut moderator = observers.join(Sight::Moderator, vec![]);
+        for w in all() {
+            observers.tell(w);
+        }
+        assert_eq!(seen(&mut spectator), public());
+        assert_eq!(seen(&mut moderator).len(), all().len());
+    }
+
+    #[test]
+    fn late_spectators_get_the_public_history() {
+        let mut observers = Observers::new();
+        let mut spectator = observers.join(Sight::Spectator, all());
+        assert_eq!(seen(&mut spectator), public());
+    }
+}
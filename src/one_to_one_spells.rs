@@ -0,0 +1,171 @@
+use btreemultimap::BTreeMultiMap;
+use serde::{Deserialize, Serialize};
+
+use crate::room::*;
+use crate::scenario::*;
+use crate::waiting::PlayerId;
+
+#[derive(Serialize, Deserialize)]
+pub struct OneToOneSpells {
+    #[serde(with = "crate::serde_multimap")]
+    spells: BTreeMultiMap<Power, RawSpell>,
+}
+
+impl Default for OneToOneSpells {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OneToOneSpells {
+    pub fn new() -> Self {
+        Self { spells: BTreeMultiMap::new() }
+    }
+
+    pub fn cast(&mut self, from: PlayerId, pray: Pray) -> bool {
+        let power = match pray.action {
+            Act::Use(power) => power,
+            _ => return false
+        };
+
+        match Self::night_act(power, &pray) {
+            Some(act) => {
+                self.spells.retain(|p, (caster, _, _)| !(*p == power && *caster == from));
+                self.spells.insert(power, (from, power, act));
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn cancel(&mut self, from: &PlayerId) -> bool {
+        let before = self.spells.len();
+        self.spells.retain(|_, (caster, _, _)| caster != from);
+        self.spells.len() != before
+    }
+
+    fn night_act(power: Power, pray: &Pray) -> Option<NightAct> {
+        match (power, pray.query.as_slice(), &pray.meta) {
+            (_, [on], Some(Meta::Has(power))) => Some(NightAct::Wicked(*on, *power)),
+            (_, [on], Some(Meta::Role(role))) => Some(NightAct::Sense(*on, role.clone())),
+            (Power::Reveal, _, _) => None,
+            (_, [on], None) => Some(NightAct::One(*on)),
+            (_, [first, second], None) => Some(NightAct::Two(*first, *second)),
+            (_, ids, None) if ids.len() > 2 => Some(NightAct::Many(ids.to_vec())),
+            _ => None
+        }
+    }
+}
+
+impl Spells for OneToOneSpells {
+    fn get(&self, power: &Power) -> Option<&NightAct> {
+        self.raw(power).map(|(_, _, act)| act)
+    }
+
+    fn raw(&self, power: &Power) -> Option<&RawSpell> {
+        self.spells.get(power)
+    }
+
+    fn raw_vec(&self, power: &Power) -> Option<Vec<&RawSpell>> {
+        self.spells.get_vec(power).map(|spells| spells.iter().collect())
+    }
+
+    fn one(&self, power: &Power) -> Option<(PlayerId, Power, PlayerId)> {
+        match self.raw(power)? {
+            (from, power, NightAct::One(on)) => Some((*from, *power, *on)),
+            _ => None
+        }
+    }
+
+    fn two(&self, power: &Power) -> Option<(PlayerId, Power, (PlayerId, PlayerId))> {
+        match self.raw(power)? {
+            (from, power, NightAct::Two(first, second)) => Some((*from, *power, (*first, *second))),
+            _ => None
+        }
+    }
+
+    fn all(&self, power: &Power) -> Option<(PlayerId, Power, Vec<PlayerId>)> {
+        let (from, power, act) = self.raw(power)?;
+        let ids = match act {
+            NightAct::One(on) | NightAct::Wicked(on, _) | NightAct::Sense(on, _) => vec![*on],
+            NightAct::Two(first, second) => vec![*first, *second],
+            NightAct::Many(ids) => ids.clone(),
+        };
+        Some((*from, *power, ids))
+    }
+
+    fn get_kv(&self, power: &Power) -> Option<(&Power, &NightAct)> {
+        self.raw(power).map(|(_, power, act)| (power, act))
+    }
+
+    fn expect1(&self, power: &Power) -> Option<&PlayerId> {
+        match self.get(power)? {
+            NightAct::One(on) => Some(on),
+            _ => None
+        }
+    }
+
+    fn expect2(&self, power: &Power) -> Option<(&PlayerId, &PlayerId)> {
+        match self.get(power)? {
+            NightAct::Two(first, second) => Some((first, second)),
+            _ => None
+        }
+    }
+
+    fn stop(&mut self, power: &Power) -> bool {
+        self.spells.remove(power).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pray(power: Power, query: Vec<PlayerId>, meta: Option<Meta>) -> Pray {
+        Pray { action: Act::Use(power), query, meta }
+    }
+
+    #[test]
+    fn each_caster_keeps_one_spell_per_power() {
+        let mut spells = OneToOneSpells::new();
+        assert!(spells.cast(1, pray(Power::Heal, vec![2], None)));
+        assert!(spells.cast(1, pray(Power::Heal, vec![3, 4], None)));
+        assert!(spells.cast(5, pray(Power::Heal, vec![6], None)));
+
+        let heals = spells.raw_vec(&Power::Heal).unwrap();
+        assert_eq!(heals.len(), 2);
+        assert_eq!(spells.two(&Power::Heal), Some((1, Power::Heal, (3, 4))));
+        assert_eq!(spells.expect2(&Power::Heal), Some((&3, &4)));
+        assert!(spells.one(&Power::Heal).is_none());
+    }
+
+    #[test]
+    fn prays_become_night_acts() {
+        let mut spells = OneToOneSpells::new();
+        assert!(spells.cast(1, pray(Power::Enquery, vec![2], None)));
+        assert!(spells.cast(3, pray(Power::Reveal, vec![4], Some(Meta::Has(Power::Heal)))));
+        assert!(spells.cast(5, pray(Power::Guard, vec![6, 7, 8], None)));
+        assert!(!spells.cast(9, pray(Power::Reveal, vec![4], None)));
+        assert!(!spells.cast(9, Pray { action: Act::Vote, query: vec![4], meta: None }));
+
+        assert_eq!(spells.one(&Power::Enquery), Some((1, Power::Enquery, 2)));
+        assert_eq!(spells.expect1(&Power::Enquery), Some(&2));
+        assert!(matches!(spells.get(&Power::Reveal), Some(NightAct::Wicked(4, Power::Heal))));
+        assert_eq!(spells.all(&Power::Reveal), Some((3, Power::Reveal, vec![4])));
+        assert_eq!(spells.all(&Power::Guard), Some((5, Power::Guard, vec![6, 7, 8])));
+    }
+
+    #[test]
+    fn spells_can_be_stopped_or_cancelled() {
+        let mut spells = OneToOneSpells::new();
+        spells.cast(1, pray(Power::Heal, vec![2], None));
+        spells.cast(1, pray(Power::Enquery, vec![2], None));
+        spells.cast(3, pray(Power::Paralyze, vec![1], None));
+
+        assert!(spells.cancel(&1));
+        assert!(!spells.cancel(&1));
+        assert!(spells.get(&Power::Heal).is_none());
+        assert!(spells.stop(&Power::Paralyze));
+        assert!(!spells.stop(&Power::Paralyze));
+    }
+}
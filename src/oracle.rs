@@ -1,15 +1,21 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use ringbuf::{Consumer, Producer, RingBuffer};
 
 pub struct TwoWayRing<S, H> {
     ask: Producer<S>,
     read: Consumer<H>,
+    link: Arc<()>,
 }
 
 impl<S, H> TwoWayRing<S, H> {
-    fn with(ask: Producer<S>, read: Consumer<H>) -> Self {
-        Self { ask, read }
+    fn with(ask: Producer<S>, read: Consumer<H>, link: Arc<()>) -> Self {
+        Self { ask, read, link }
+    }
+
+    /// Whether the other end of the ring has been dropped.
+    pub fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.link) == 1
     }
 
     pub fn read(&mut self) -> Option<H> {
@@ -30,8 +36,9 @@ impl Oracle {
         let (god_prod, god_cons) = rb_god.split();
         let (player_prod, player_cons) = rb_player.split();
 
-        let oracle: TwoWayRing<L, R> = TwoWayRing::with(god_prod, player_cons);
-        let god: TwoWayRing<R, L> = TwoWayRing::with(player_prod, god_cons);
+        let link = Arc::new(());
+        let oracle: TwoWayRing<L, R> = TwoWayRing::with(god_prod, player_cons, link.clone());
+        let god: TwoWayRing<R, L> = TwoWayRing::with(player_prod, god_cons, link);
 
         (oracle, god)
     }
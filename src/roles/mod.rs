@@ -0,0 +1,368 @@
+use std::{collections::{BTreeMap, BTreeSet}, ops::RangeInclusive};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{night::Effect, scenario::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Faction {
+    City,
+    Mafia,
+    SerialKiller,
+    Jester,
+    Survivor,
+}
+
+impl Faction {
+    pub const ALL: [Faction; 5] = [Faction::City, Faction::Mafia, Faction::SerialKiller, Faction::Jester, Faction::Survivor];
+
+    pub fn marker(&self) -> Option<Power> {
+        match self {
+            Faction::City => None,
+            Faction::Mafia => Some(Power::Mafia),
+            Faction::SerialKiller => Some(Power::Custom(Tag::new("SerialKiller"))),
+            Faction::Jester => Some(Power::Custom(Tag::new("Jester"))),
+            Faction::Survivor => Some(Power::Custom(Tag::new("Survivor"))),
+        }
+    }
+
+    pub fn decisive(&self) -> bool {
+        matches!(self, Faction::City | Faction::Mafia | Faction::SerialKiller)
+    }
+
+    /// Whether this faction's win ends the game; a hanged Jester does not wait for the others.
+    pub fn ends(&self) -> bool {
+        self.decisive() || *self == Faction::Jester
+    }
+
+    pub fn wins(&self, census: &Census) -> bool {
+        let mafia = census.living(Faction::Mafia);
+        let killers = census.living(Faction::SerialKiller);
+        match self {
+            Faction::City => census.living(Faction::City) > 0 && mafia == 0 && killers == 0,
+            Faction::Mafia => mafia > 0 && killers == 0 && mafia >= census.total - mafia,
+            Faction::SerialKiller => killers > 0 && mafia == 0 && census.total <= killers + 1,
+            Faction::Jester => census.hanged.contains(self),
+            Faction::Survivor => census.living(Faction::Survivor) > 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Census {
+    pub living: BTreeMap<Faction, usize>,
+    pub total: usize,
+    pub hanged: BTreeSet<Faction>,
+}
+
+impl Census {
+    pub fn new(factions: impl Iterator<Item = Faction>, hanged: BTreeSet<Faction>) -> Self {
+        let mut census = Self { hanged, ..Self::default() };
+        for faction in factions {
+            *census.living.entry(faction).or_insert(0) += 1;
+            census.total += 1;
+        }
+        census
+    }
+
+    pub fn living(&self, faction: Faction) -> usize {
+        self.living.get(&faction).cloned().unwrap_or(0)
+    }
+
+    /// True when no player of a decisive faction is left alive.
+    pub fn wiped_out(&self) -> bool {
+        Faction::ALL.iter().filter(|f| f.decisive()).all(|f| self.living(*f) == 0)
+    }
+
+    /// True when a lone mafioso and a lone Serial Killer are left: neither can outvote the other.
+    pub fn stalemate(&self) -> bool {
+        self.total == 2 && self.living(Faction::Mafia) == 1 && self.living(Faction::SerialKiller) == 1
+    }
+
+    pub fn winners(&self) -> BTreeSet<Faction> {
+        let decided = Faction::ALL.iter().any(|f| f.ends() && f.wins(self));
+        if !decided && !self.wiped_out() {
+            return BTreeSet::new()
+        }
+        Faction::ALL.iter().filter(|f| f.wins(self)).cloned().collect()
+    }
+
+    /// A wipe-out that no neutral faction wins, or a stalemate, ends the game in a draw.
+    pub fn state(&self) -> State {
+        match self.winners() {
+            winners if winners.is_empty() && (self.wiped_out() || self.stalemate()) => State::Draw,
+            winners => winners.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Targeting {
+    pub targets: RangeInclusive<usize>,
+    pub on_self: bool,
+    pub graveyard: bool,
+    /// With fewer living players than this, only one target may be chosen.
+    pub crowd: usize,
+    /// Targeting oneself spends this passive power, so it can be done only while the power lasts.
+    pub self_cost: Option<Power>,
+}
+
+impl Targeting {
+    pub fn one() -> Self {
+        Self { targets: 1..=1, on_self: false, graveyard: false, crowd: 0, self_cost: None }
+    }
+
+    pub fn up_to(n: usize) -> Self {
+        Self { targets: 1..=n, on_self: false, graveyard: false, crowd: 0, self_cost: None }
+    }
+
+    pub fn exactly(n: usize) -> Self {
+        Self { targets: n..=n, on_self: false, graveyard: false, crowd: 0, self_cost: None }
+    }
+
+    pub fn dead() -> Self {
+        Self { targets: 1..=1, on_self: false, graveyard: true, crowd: 0, self_cost: None }
+    }
+
+    pub fn with_self(mut self) -> Self {
+        self.on_self = true;
+        self
+    }
+
+    pub fn with_self_once(mut self, cost: Power) -> Self {
+        self.on_self = true;
+        self.self_cost = Some(cost);
+        self
+    }
+
+    pub fn crowd(mut self, players: usize) -> Self {
+        self.crowd = players;
+        self
+    }
+
+    pub fn allowed(&self, players: usize) -> RangeInclusive<usize> {
+        match players < self.crowd {
+            true => *self.targets.start()..=1.min(*self.targets.end()),
+            false => self.targets.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ability {
+    pub power: Power,
+    pub effect: Effect,
+    pub targeting: Targeting,
+    pub priority: u8,
+    pub required: bool,
+    pub once: bool,
+}
+
+impl Ability {
+    pub fn new(power: Power, effect: Effect, targeting: Targeting, priority: u8) -> Self {
+        Self { power, effect, targeting, priority, required: true, once: false }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    pub fn once(mut self) -> Self {
+        self.once = true;
+        self.optional()
+    }
+}
+
+pub trait Role: Send + Sync {
+    fn name(&self) -> &str;
+    fn faction(&self) -> Faction;
+    fn active(&self) -> Vec<Ability>;
+
+    fn passive(&self) -> Vec<Power> {
+        vec![]
+    }
+
+    fn powers(&self) -> Vec<Power> {
+        let mut powers = Vec::new();
+        powers.extend(self.faction().marker());
+        powers.extend(self.passive());
+        powers.extend(self.active().into_iter().map(|a| a.power));
+        powers
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    roles: BTreeMap<String, Box<dyn Role>>,
+    abilities: BTreeMap<Power, Ability>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, role: impl Role + 'static) -> Result<(), Error> {
+        let name = role.name().to_owned();
+        if self.roles.contains_key(&name) {
+            return Err(Config::DuplicateRole(name).into())
+        }
+
+        let abilities = role.active();
+        let mut seen = BTreeSet::new();
+        let taken = abilities.iter()
+            .find(|a| self.abilities.contains_key(&a.power) || !seen.insert(a.power));
+        if let Some(ability) = taken {
+            return Err(Config::DuplicateAbility(ability.power).into())
+        }
+
+        for ability in abilities {
+            self.abilities.insert(ability.power, ability);
+        }
+        self.roles.insert(name, Box::new(role));
+        Ok(())
+    }
+
+    pub fn with(mut self, role: impl Role + 'static) -> Result<Self, Error> {
+        self.register(role)?;
+        Ok(self)
+    }
+
+    pub fn role(&self, name: &str) -> Option<&dyn Role> {
+        self.roles.get(name).map(|r| r.as_ref())
+    }
+
+    pub fn roles(&self) -> impl Iterator<Item = &dyn Role> {
+        self.roles.values().map(|r| r.as_ref())
+    }
+
+    pub fn ability(&self, power: &Power) -> Option<&Ability> {
+        self.abilities.get(power)
+    }
+
+    pub fn abilities(&self) -> impl Iterator<Item = &Ability> {
+        self.abilities.values()
+    }
+
+    pub fn night(&self, power: &Power) -> bool {
+        self.abilities.contains_key(power)
+    }
+
+    pub fn required(&self, powers: &[Power]) -> bool {
+        powers.iter().any(|p| self.ability(p).is_some_and(|a| a.required))
+    }
+
+    pub fn knows(&self, power: &Power) -> bool {
+        self.night(power) || self.roles().any(|r| r.passive().contains(power) || r.faction().marker() == Some(*power))
+    }
+
+    pub fn faction(&self, role: &str) -> Option<Faction> {
+        self.role(role).map(|r| r.faction())
+    }
+
+    /// The role whose powers are exactly `powers`, in any order.
+    pub fn find(&self, powers: &[Power]) -> Option<&dyn Role> {
+        let wanted = powers.iter().collect::<BTreeSet<_>>();
+        self.roles()
+            .find(|r| r.powers().len() == powers.len() && r.powers().iter().collect::<BTreeSet<_>>() == wanted)
+    }
+
+    /// The role of a faction with no powers besides its marker, given to recruits.
+    pub fn plain(&self, faction: Faction) -> Option<&dyn Role> {
+        self.roles()
+            .find(|r| r.faction() == faction && r.active().is_empty() && r.passive().is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::Config, games::classic::roles::{self, Boss, Doctor}, night::Kill};
+
+    use super::*;
+
+    use Faction::*;
+
+    struct Copycat;
+
+    impl Role for Copycat {
+        fn name(&self) -> &str { "Copycat" }
+        fn faction(&self) -> Faction { Faction::Mafia }
+        fn active(&self) -> Vec<Ability> {
+            vec![Ability::new(Power::NightKill, Effect::Kill(Kill::Shot), Targeting::one(), 4)]
+        }
+    }
+
+    fn census(living: &[(Faction, usize)], hanged: &[Faction]) -> Census {
+        let factions = living.iter().flat_map(|(f, n)| std::iter::repeat_n(*f, *n));
+        Census::new(factions, hanged.iter().cloned().collect())
+    }
+
+    fn winners(living: &[(Faction, usize)], hanged: &[Faction]) -> Vec<Faction> {
+        census(living, hanged).winners().into_iter().collect()
+    }
+
+    #[test]
+    fn the_game_goes_on_while_nobody_wins() {
+        assert_eq!(census(&[(City, 5), (Mafia, 2)], &[]).state(), State::Undecided);
+        assert_eq!(census(&[(City, 3), (SerialKiller, 1)], &[]).state(), State::Undecided);
+    }
+
+    #[test]
+    fn the_city_wins_once_the_killers_are_gone() {
+        assert_eq!(census(&[(City, 3)], &[]).state(), State::CityWon);
+        assert!(winners(&[(City, 3), (SerialKiller, 1)], &[]).is_empty());
+    }
+
+    #[test]
+    fn the_mafia_wins_at_parity() {
+        assert_eq!(census(&[(City, 2), (Mafia, 2)], &[]).state(), State::MafiaWon);
+        assert!(winners(&[(City, 3), (Mafia, 2)], &[]).is_empty());
+        assert!(winners(&[(City, 1), (Mafia, 2), (SerialKiller, 1)], &[]).is_empty());
+    }
+
+    #[test]
+    fn the_serial_killer_wins_a_duel_but_not_against_the_mafia() {
+        assert_eq!(winners(&[(City, 1), (SerialKiller, 1)], &[]), vec![SerialKiller]);
+        assert_eq!(census(&[(Mafia, 1), (SerialKiller, 1)], &[]).state(), State::Draw);
+        assert_eq!(census(&[(Mafia, 2), (SerialKiller, 1)], &[]).state(), State::Undecided);
+    }
+
+    #[test]
+    fn neutrals_win_alongside_a_decisive_faction() {
+        assert_eq!(winners(&[(City, 3), (Survivor, 1)], &[Jester]), vec![City, Jester, Survivor]);
+        assert_eq!(census(&[(City, 3), (Survivor, 1)], &[]).state(), State::Won(BTreeSet::from([City, Survivor])));
+    }
+
+    #[test]
+    fn a_hanged_jester_ends_the_game() {
+        assert_eq!(winners(&[(City, 5), (Mafia, 2)], &[Jester]), vec![Jester]);
+        assert_eq!(winners(&[(City, 5), (Mafia, 2), (Survivor, 1)], &[Jester]), vec![Jester, Survivor]);
+    }
+
+    #[test]
+    fn a_wiped_out_table_ends_the_game() {
+        assert_eq!(winners(&[(Survivor, 1), (Jester, 1)], &[]), vec![Survivor]);
+        assert_eq!(census(&[(Jester, 1)], &[]).state(), State::Draw);
+        assert_eq!(census(&[], &[]).state(), State::Draw);
+    }
+
+    #[test]
+    fn roles_and_abilities_are_registered_once() {
+        let registry = Registry::new().with(Boss).and_then(|r| r.with(Doctor)).unwrap();
+        assert_eq!(registry.with(Boss).err(), Some(Config::DuplicateRole("Boss".to_owned()).into()));
+
+        let registry = Registry::new().with(Boss).unwrap();
+        assert_eq!(registry.with(Copycat).err(), Some(Config::DuplicateAbility(Power::NightKill).into()));
+    }
+
+    #[test]
+    fn a_role_is_found_by_its_powers_in_any_order() {
+        let registry = roles::registry();
+        let mut powers = Boss.powers();
+        powers.reverse();
+        assert_eq!(registry.find(&powers).map(|r| r.name()), Some("Boss"));
+        assert!(registry.find(&[Power::Heal, Power::Enquery]).is_none());
+        assert!(registry.knows(&Power::Disguise));
+    }
+}
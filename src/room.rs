@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, vec};
+use serde::{Deserialize, Serialize};
 
 use crate::{scenario::*, waiting::{PlayerId}};
 
@@ -7,6 +7,7 @@ pub trait Room {
     fn has(&self, id: &PlayerId, power: &Power) -> bool;
     fn drop_kinks<const N: usize>(&mut self, id: &PlayerId, kinks: [Power; N]);
     fn kinks(&self, id: &PlayerId) -> Vec<Power>;
+    fn role(&self, id: &PlayerId) -> Option<&str>;
     fn total(&self) -> usize;
     fn count(&self, power: &Power) -> usize;
     fn remove(&mut self, id: &PlayerId) -> Vec<Power>;
@@ -14,11 +15,13 @@ pub trait Room {
     fn by_power(&self, power: &Power) -> Vec<PlayerId>;
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum NightAct {
     One(PlayerId),
     Two(PlayerId, PlayerId),
-    Wicked(PlayerId, Power)
+    Many(Vec<PlayerId>),
+    Wicked(PlayerId, Power),
+    Sense(PlayerId, String),
 }
 
 
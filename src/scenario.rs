@@ -1,10 +1,15 @@
-use std::{collections::{BTreeMap, BTreeSet}, vec};
+use std::{collections::{BTreeMap, BTreeSet}, fmt, ops::RangeInclusive};
 
 use btreemultimap::BTreeMultiMap;
+use rand_chacha::ChaCha8Rng;
 
-use crate::waiting::PlayerId;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-pub type Error = String;
+use crate::{cards::Card, elections::tie::Verdict, roles::Faction, waiting::{PlayerId, Token}};
+
+pub use crate::error::{Config, Error, Lookup, Rule};
+
+pub type GameRng = ChaCha8Rng;
 
 pub enum ShootingResult {
     Killed(PlayerId),
@@ -13,7 +18,57 @@ pub enum ShootingResult {
 }
 
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct Tag([u8; Tag::SIZE]);
+
+impl Tag {
+    pub const SIZE: usize = 16;
+
+    pub const fn new(name: &str) -> Self {
+        let bytes = name.as_bytes();
+        assert!(bytes.len() <= Self::SIZE, "Tag names are at most 16 bytes");
+        let mut tag = [0; Self::SIZE];
+        let mut i = 0;
+        while i < bytes.len() {
+            tag[i] = bytes[i];
+            i += 1;
+        }
+        Self(tag)
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.is_empty() || name.len() > Self::SIZE {
+            return None
+        }
+        Some(Self::new(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = self.0.iter().position(|b| *b == 0).unwrap_or(Self::SIZE);
+        std::str::from_utf8(&self.0[..len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Tag::parse(&name).ok_or_else(|| de::Error::custom(format!("Invalid tag: {}", name)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
 pub enum Power {
     Guard,
     Paralyze,
@@ -29,57 +84,143 @@ pub enum Power {
     DodgeCommando,
     Mafia,
     DayShield,
+    Vest,
+
+    Custom(Tag),
 }
 
-impl Power {
-    pub fn night(&self) -> bool {
-        self.active().contains(self)
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
+pub enum Act {
+    Nominate,
+    Vote,
+    Shoot,
+    Talk,
+    Draw,
+    LastMove,
+    Use(Power),
+}
+
+impl Act {
+    pub fn public(&self) -> bool {
+        !matches!(self, Act::Use(_))
     }
 
-    pub fn active(&self) -> [Power; 9] {
-        use Power::*;
-        [NightKill, Reveal, Paralyze, Heal, Enquery, Guard, HandGun, HandFakeGun, ShotOnKill]
+    pub fn targets(&self) -> RangeInclusive<usize> {
+        use Act::*;
+        match self {
+            Nominate | Vote => 0..=1,
+            Talk | Draw => 0..=0,
+            LastMove => 0..=2,
+            Shoot | Use(_) => 1..=1,
+        }
     }
+}
 
-    pub fn passive(&self) -> [Power; 4] {
-        use Power::*;
-        [Disguise, DodgeCommando, Mafia, DayShield]
+impl fmt::Debug for Act {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Act::*;
+        match self {
+            Nominate => write!(f, "Nominate"),
+            Vote => write!(f, "Vote"),
+            Shoot => write!(f, "Shoot"),
+            Talk => write!(f, "Talk"),
+            Draw => write!(f, "Draw"),
+            LastMove => write!(f, "LastMove"),
+            Use(power) => write!(f, "{:?}", power),
+        }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum HolyMessage {
+    Seated(PlayerId),
+    Session(Token),
+    Declared(Declaration),
     Assigned(Vec<Power>),
     YouHaveGun,
     YouAreBoss,
     IsMafia(PlayerId, bool),
+    MafiaAmong(Vec<PlayerId>, usize),
+    Said(PlayerId, Channel, String),
+    Rejected { reason: String },
+    Nominated { from: PlayerId, on: PlayerId },
+    Voted { from: PlayerId, on: PlayerId },
+    Abstained { from: PlayerId, action: Act },
+    Drew { from: PlayerId, lot: usize },
+    Tally(Tally),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tally {
+    pub action: Act,
+    pub votes: BTreeMap<PlayerId, BTreeSet<PlayerId>>,
+    pub abstained: BTreeSet<PlayerId>,
+    pub threshold: usize,
+    pub crossed: Vec<PlayerId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Channel {
+    Public,
+    Mafia,
+    LastWords,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Meta {
-    Has(Power)
+    Has(Power),
+    Role(String),
+    Say(Channel, String),
+    Lot(usize),
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Pray {
-    pub action: Power,
+    pub action: Act,
     pub query: Vec<PlayerId>,
     pub meta: Option<Meta>
 }
 
-// impl Pray {
-//     fn is_consistent(&self) -> bool {
-//         use Power::*;
-//         match (&self.action, &self.query, &self.meta) {
-//             (Reveal, ids, Some(Meta::Has(Power::Heal|Power::DayShield|Power::Enquery|Power::ShotOnKill))) if ids.len() == 1 => true,
-//             (NightKill|Paralyze|Heal|Enquery|HandGun|HandFakeGun|DayShield, ids, None) if ids.len() <= 2 => true,
-//             (ShotOnKill, ids, None) if ids.len() == 1 => true,
-//             _ => false
-//         }
-//     }
+impl Pray {
+    pub fn is_consistent(&self) -> Result<(), Error> {
+        use Power::*;
+        match (&self.action, &self.meta) {
+            (Act::Use(power @ (Disguise | DodgeCommando | Mafia | DayShield | Vest)), _) => return Err(Rule::NotAnAction(*power).into()),
+            (Act::Use(Reveal), Some(Meta::Has(Heal | DayShield | Enquery | ShotOnKill))) => {},
+            (Act::Use(Reveal), Some(Meta::Has(power))) => return Err(Rule::Unrevealable(*power).into()),
+            (Act::Use(Reveal), _) => return Err(Rule::NoGuess.into()),
+            (Act::Talk, Some(Meta::Say(_, _))) => {},
+            (Act::Talk, _) => return Err(Rule::NoMessage.into()),
+            (Act::Draw, Some(Meta::Lot(_))) => {},
+            (Act::Draw, _) => return Err(Rule::NoLot.into()),
+            (Act::Use(Custom(_)), None | Some(Meta::Has(_) | Meta::Role(_))) => {},
+            (_, Some(_)) => return Err(Rule::NoMeta(self.action).into()),
+            (_, None) => {},
+        }
+
+        if self.action.public() {
+            self.has_targets(self.action.targets())?;
+        }
+
+        let distinct = self.query.iter().collect::<BTreeSet<_>>();
+        if distinct.len() != self.query.len() {
+            return Err(Rule::SameTargets.into())
+        }
+        Ok(())
+    }
+
+    pub fn has_targets(&self, targets: RangeInclusive<usize>) -> Result<(), Error> {
+        if !targets.contains(&self.query.len()) {
+            let (min, max) = targets.into_inner();
+            return Err(Rule::Targets { action: self.action, min, max, got: self.query.len() }.into())
+        }
+        Ok(())
+    }
 
-//     pub fn on(&self) -> &Vec<PlayerId> {
-//         &self.query
-//     }
-// }
+    pub fn on(&self) -> &Vec<PlayerId> {
+        &self.query
+    }
+}
 
 pub type Messages = BTreeMultiMap<PlayerId, HolyMessage>;
 
@@ -93,17 +234,23 @@ pub trait News {
 pub trait DeathBallot {
     fn list(&self) -> &BTreeSet<PlayerId>;
     fn hang(&mut self, from: PlayerId, on: PlayerId) -> bool;
-    fn dead(&self) -> Option<PlayerId>;
+    fn abstain(&mut self, from: PlayerId) -> bool;
+    fn dead(&self, living: usize) -> Option<PlayerId>;
+    fn leaders(&self, living: usize) -> Vec<PlayerId>;
+    fn tally(&self, living: usize) -> Tally;
 }
 
 pub trait Defendence {
     type Ballot: DeathBallot;
 
     fn nominate(&mut self, from: PlayerId, on: PlayerId) -> bool;
-    fn result(&self) -> Option<Self::Ballot>;
+    fn abstain(&mut self, from: PlayerId) -> bool;
+    /// The death vote for the players who crossed the threshold, or `None` when nobody did.
+    fn result(&self, living: usize) -> Result<Option<Self::Ballot>, Error>;
+    fn tally(&self, living: usize) -> Tally;
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
 pub enum CityState {
     Debate,
     Defend,
@@ -112,18 +259,58 @@ pub enum CityState {
     Done(State)
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
 pub enum State {
     MafiaWon,
     CityWon,
-    Undecided
+    Undecided,
+    Won(BTreeSet<Faction>),
+    Draw,
+}
+
+impl State {
+    pub fn winners(&self) -> BTreeSet<Faction> {
+        match self {
+            State::MafiaWon => BTreeSet::from([Faction::Mafia]),
+            State::CityWon => BTreeSet::from([Faction::City]),
+            State::Undecided | State::Draw => BTreeSet::new(),
+            State::Won(winners) => winners.clone(),
+        }
+    }
 }
 
-#[derive(Clone)]
+impl From<BTreeSet<Faction>> for State {
+    fn from(winners: BTreeSet<Faction>) -> Self {
+        let factions = winners.iter().collect::<Vec<_>>();
+        match factions.as_slice() {
+            [] => State::Undecided,
+            [Faction::Mafia] => State::MafiaWon,
+            [Faction::City] => State::CityWon,
+            _ => State::Won(winners),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Declaration {
     Out(PlayerId),
     StateChanged(CityState),
-    FakeGun(PlayerId)
+    FakeGun(PlayerId),
+    Seed(u64),
+    Seats(Vec<PlayerId>),
+    OnTrial(Vec<PlayerId>),
+    LotDraw(Vec<PlayerId>),
+    CardDrawn(PlayerId, Card),
+    CardPlayed(PlayerId, Card, Vec<PlayerId>),
+    Back(PlayerId),
+    Exposed(PlayerId),
+    Verdict(Verdict),
+}
+
+impl Declaration {
+    pub fn public(&self) -> bool {
+        !matches!(self, Declaration::Seed(_))
+    }
 }
 
 pub type Day = usize;
@@ -134,5 +321,5 @@ pub trait Scenario {
     fn next(&mut self) -> CityState;
     fn events(&self) -> &Vec<(Day, CityState, Declaration)>;
 
-    fn process(&mut self);
+    fn process(&mut self) -> bool;
 }
\ No newline at end of file
@@ -0,0 +1,25 @@
+use btreemultimap::BTreeMultiMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<K, V, S>(map: &BTreeMultiMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMultiMap<K, V>, D::Error>
+where
+    K: Ord + Deserialize<'de>,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    let pairs = Vec::<(K, V)>::deserialize(deserializer)?;
+    let mut map = BTreeMultiMap::new();
+    for (k, v) in pairs {
+        map.insert(k, v);
+    }
+    Ok(map)
+}
@@ -0,0 +1,384 @@
+use std::{io::ErrorKind, net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs}, sync::{Arc, Mutex}, thread, time::Duration};
+
+use serde_json::{Map, Value};
+use tungstenite::{accept_hdr, handshake::server::{Callback, ErrorResponse, Request, Response}, protocol::{frame::coding::CloseCode, CloseFrame}, Message, WebSocket};
+
+use crate::{clock::SystemClock, games::registry::Scenarios, lobby::{Code, Lobbies, Session}, observer::{Watch, WatcherRef}, error::{io, serialization}, scenario::*, timer::Timetable, waiting::{OracleRef, Token}};
+
+const POLL: Duration = Duration::from_millis(20);
+const HANDSHAKE: Duration = Duration::from_secs(5);
+
+type Shared = Arc<Mutex<Lobbies<SystemClock>>>;
+
+pub struct Server {
+    listener: TcpListener,
+    players: usize,
+    scenario: String,
+    scenarios: Scenarios,
+    timetable: Timetable,
+}
+
+enum Route {
+    List,
+    Create(Option<String>, usize, Value),
+    Join(Code),
+    Watch(Code),
+    Moderate(Code, String),
+    Resume(Token),
+    Any,
+}
+
+/// What a bare connection to `/` joins.
+#[derive(Clone)]
+struct Table {
+    scenario: String,
+    players: usize,
+}
+
+enum Seat {
+    Listed,
+    Watcher(WatcherRef),
+    Player(Session, OracleRef),
+}
+
+struct Path<'a>(&'a mut String);
+
+impl Callback for Path<'_> {
+    fn on_request(self, req: &Request, res: Response) -> Result<Response, ErrorResponse> {
+        *self.0 = req.uri().path_and_query().map_or("/", |p| p.as_str()).to_owned();
+        Ok(res)
+    }
+}
+
+impl Route {
+    fn parse(uri: &str) -> Option<Self> {
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let parts = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        match parts.as_slice() {
+            [""] => Some(Route::Any),
+            ["lobbies"] => Some(Route::List),
+            ["new", n] => Some(Route::Create(None, n.parse().ok()?, options(query)?)),
+            ["new", scenario, n] => Some(Route::Create(Some(scenario.to_string()), n.parse().ok()?, options(query)?)),
+            ["join", code] => Some(Route::Join(code.to_string())),
+            ["watch", code] => Some(Route::Watch(code.to_string())),
+            ["resume", token] => Some(Route::Resume(token.to_string())),
+            ["moderate", code, key] => Some(Route::Moderate(code.to_string(), key.to_string())),
+            _ => None
+        }
+    }
+}
+
+/// Reads lobby options from a query such as `last_move=true&voting.tie=Revote`.
+/// Values are read as JSON, falling back to plain strings, and dots nest keys.
+fn options(query: &str) -> Option<Value> {
+    let mut options = Value::Null;
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=')?;
+        let mut slot = &mut options;
+        for part in key.split('.') {
+            if slot.is_null() {
+                *slot = Value::Object(Map::new());
+            }
+            slot = slot.as_object_mut()?.entry(part.to_owned()).or_insert(Value::Null);
+        }
+        *slot = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()));
+    }
+    Some(options)
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, players: usize) -> Result<Self, Error> {
+        let scenarios = Scenarios::default();
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| Error::Io(format!("Unable to bind: {}", e)))?;
+        Ok(Self { listener, players, scenario: Scenarios::DEFAULT.to_owned(), scenarios, timetable: Timetable::default() })
+    }
+
+    pub fn scenario(mut self, name: &str) -> Result<Self, Error> {
+        self.scenarios.check(name, self.players)?;
+        self.scenario = name.to_owned();
+        Ok(self)
+    }
+
+    pub fn scenarios(mut self, scenarios: Scenarios) -> Self {
+        self.scenarios = scenarios;
+        self
+    }
+
+    pub fn timetable(mut self, timetable: Timetable) -> Self {
+        self.timetable = timetable;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr()
+            .map_err(|e| Error::Io(format!("Unable to read the local address: {}", e)))
+    }
+
+    pub fn run(self) -> Result<(), Error> {
+        self.scenarios.check(&self.scenario, self.players)?;
+        let lobbies = Lobbies::new(SystemClock::new(), self.timetable).scenarios(self.scenarios.clone());
+        let lobbies = Arc::new(Mutex::new(lobbies));
+
+        let games = lobbies.clone();
+        thread::spawn(move || host(games, POLL));
+
+        let table = Table { scenario: self.scenario.clone(), players: self.players };
+        for stream in self.listener.incoming() {
+            let (lobbies, table) = (lobbies.clone(), table.clone());
+            thread::spawn(move || {
+                let (ws, route) = match stream.map_err(io).and_then(Self::handshake) {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        eprintln!("Connection refused: {}", e);
+                        return
+                    }
+                };
+
+                if let Err(e) = table.welcome(&lobbies, ws, route) {
+                    eprintln!("Unable to seat the player: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn handshake(stream: TcpStream) -> Result<(WebSocket<TcpStream>, Route), Error> {
+        stream.set_read_timeout(Some(HANDSHAKE)).map_err(io)?;
+        let mut path = String::new();
+        let ws = accept_hdr(stream, Path(&mut path)).map_err(io)?;
+        ws.get_ref().set_read_timeout(Some(POLL)).map_err(io)?;
+
+        let route = Route::parse(&path)
+            .ok_or_else(|| Lookup::Path(path.clone()))?;
+        Ok((ws, route))
+    }
+}
+
+impl Table {
+    fn welcome(&self, lobbies: &Shared, mut ws: WebSocket<TcpStream>, route: Route) -> Result<(), Error> {
+        let mut guard = lobbies.lock().map_err(io)?;
+        let seat = self.seat(&mut guard, &mut ws, route);
+        drop(guard);
+
+        let (session, oracle) = match seat {
+            Ok(Seat::Player(session, oracle)) => (session, oracle),
+            Ok(Seat::Watcher(watcher)) => {
+                watch(ws, watcher);
+                return Ok(())
+            },
+            Ok(Seat::Listed) => return Ok(()),
+            Err(e) => {
+                refuse(ws, &e);
+                return Err(e)
+            },
+        };
+
+        let oracle = bridge(ws, oracle);
+        if let Ok(mut lobbies) = lobbies.lock() {
+            lobbies.leave(&session, oracle);
+        }
+        Ok(())
+    }
+
+    fn seat(&self, lobbies: &mut Lobbies<SystemClock>, ws: &mut WebSocket<TcpStream>, route: Route) -> Result<Seat, Error> {
+        let (session, oracle) = match route {
+            Route::List => {
+                let list = serde_json::to_string(&lobbies.list()).map_err(serialization)?;
+                ws.send(Message::Text(list)).map_err(io)?;
+                ws.close(None).map_err(io)?;
+                return Ok(Seat::Listed)
+            },
+            Route::Watch(code) => return Ok(Seat::Watcher(lobbies.observe(&code, None)?)),
+            Route::Moderate(code, key) => return Ok(Seat::Watcher(lobbies.observe(&code, Some(&key))?)),
+            Route::Resume(token) => lobbies.resume(&token)?,
+            Route::Create(scenario, players, options) => {
+                let scenario = scenario.as_deref().unwrap_or(&self.scenario);
+                let code = announce(lobbies, scenario, players, options)?;
+                join(lobbies, ws, &code)?
+            },
+            Route::Join(code) => join(lobbies, ws, &code)?,
+            Route::Any => {
+                let code = match lobbies.open(&self.scenario, self.players) {
+                    Some(code) => code,
+                    None => announce(lobbies, &self.scenario, self.players, Value::Null)?,
+                };
+                join(lobbies, ws, &code)?
+            },
+        };
+        Ok(Seat::Player(session, oracle))
+    }
+}
+
+fn refuse(mut ws: WebSocket<TcpStream>, reason: &Error) {
+    let frame = CloseFrame { code: CloseCode::Policy, reason: reason.to_string().into() };
+    let _ = ws.close(Some(frame));
+    let _ = ws.flush();
+}
+
+fn join(lobbies: &mut Lobbies<SystemClock>, ws: &mut WebSocket<TcpStream>, code: &str) -> Result<(Session, OracleRef), Error> {
+    let info = lobbies.info(code);
+    let seat = lobbies.reserve(code)?;
+
+    if let Some(mut info) = info {
+        info.joined += 1;
+        let text = serde_json::to_string(&info).map_err(serialization)?;
+        ws.send(Message::Text(text)).map_err(io)?;
+    }
+    Ok(seat)
+}
+
+fn announce(lobbies: &mut Lobbies<SystemClock>, scenario: &str, players: usize, options: Value) -> Result<Code, Error> {
+    let code = lobbies.create_with(scenario, players, options)?;
+    if let Some(key) = lobbies.moderator_key(&code) {
+        println!("Lobby {} ({}) is open, moderate it at /moderate/{}/{}", code, scenario, code, key);
+    }
+    Ok(code)
+}
+
+fn host(lobbies: Shared, poll: Duration) {
+    loop {
+        match lobbies.lock() {
+            Ok(mut lobbies) => {
+                for (code, state) in lobbies.tick() {
+                    println!("Game {} is over: {:?}", code, state);
+                }
+                for code in lobbies.reap() {
+                    println!("Lobby {} was abandoned", code);
+                }
+            },
+            Err(_) => return,
+        }
+        thread::sleep(poll);
+    }
+}
+
+fn is_over(msg: &HolyMessage) -> bool {
+    matches!(msg, HolyMessage::Declared(Declaration::StateChanged(CityState::Done(_))))
+}
+
+fn watch(mut ws: WebSocket<TcpStream>, mut watcher: WatcherRef) {
+    loop {
+        match ws.read() {
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(_) => return,
+        }
+
+        while let Some(w) = watcher.read() {
+            let text = match serde_json::to_string(&w) {
+                Ok(text) => text,
+                Err(_) => continue
+            };
+            if ws.send(Message::Text(text)).is_err() {
+                return
+            }
+            if let Watch::Declared(_, _, Declaration::StateChanged(CityState::Done(_))) = w {
+                let _ = ws.close(None);
+                let _ = ws.flush();
+                return
+            }
+        }
+    }
+}
+
+fn bridge(mut ws: WebSocket<TcpStream>, mut oracle: OracleRef) -> OracleRef {
+    'conn: loop {
+        match ws.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<Pray>(&text) {
+                Ok(pray) => {
+                    oracle.tell(pray);
+                },
+                Err(e) => {
+                    let reason = serialization(format!("Invalid pray: {}", e)).to_string();
+                    let text = serde_json::to_string(&HolyMessage::Rejected { reason }).unwrap_or_default();
+                    if ws.send(Message::Text(text)).is_err() {
+                        break
+                    }
+                },
+            },
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(_) => break,
+        }
+
+        while let Some(msg) = oracle.read() {
+            let text = match serde_json::to_string(&msg) {
+                Ok(text) => text,
+                Err(_) => continue
+            };
+            if ws.send(Message::Text(text)).is_err() {
+                break 'conn
+            }
+            if is_over(&msg) {
+                let _ = ws.close(None);
+                let _ = ws.flush();
+                break 'conn
+            }
+        }
+    }
+    oracle
+}
+
+#[cfg(test)]
+mod tests {
+    use tungstenite::client;
+
+    use crate::lobby::LobbyInfo;
+
+    use super::*;
+
+    fn serve(players: usize) -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0", players).unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    fn open(addr: SocketAddr, path: &str) -> WebSocket<TcpStream> {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(HANDSHAKE / 2)).unwrap();
+        client(format!("ws://{}{}", addr, path), stream).unwrap().0
+    }
+
+    fn read<T: serde::de::DeserializeOwned>(ws: &mut WebSocket<TcpStream>) -> T {
+        match ws.read().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected frame {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_player_creates_a_lobby_and_gets_rejections_back() {
+        let addr = serve(6);
+        let mut ws = open(addr, "/new/6");
+        let info: LobbyInfo = read(&mut ws);
+        assert_eq!((info.joined, info.limit, info.scenario.as_str()), (1, 6, "classic"));
+
+        ws.send(Message::Text("not a pray".to_owned())).unwrap();
+        assert!(matches!(read(&mut ws), HolyMessage::Rejected { .. }));
+
+        let mut list = open(addr, "/lobbies");
+        let lobbies: Vec<LobbyInfo> = read(&mut list);
+        assert_eq!(lobbies.len(), 1);
+        assert_eq!(lobbies[0].code, info.code);
+    }
+
+    #[test]
+    fn an_idle_connection_does_not_hold_up_the_others() {
+        let addr = serve(6);
+        let _idle = TcpStream::connect(addr).unwrap();
+        let mut ws = open(addr, "/new/6");
+        let info: LobbyInfo = read(&mut ws);
+        assert_eq!(info.joined, 1);
+    }
+
+    #[test]
+    fn unknown_paths_are_refused() {
+        let addr = serve(6);
+        let mut ws = open(addr, "/nowhere");
+        assert!(matches!(ws.read(), Ok(Message::Close(Some(_))) | Err(_)));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt};
+
+use rand::{Rng, SeedableRng};
+
+use crate::{bots::{heuristic::HeuristicBot, random::RandomBot, Bot, BotSeat}, games::classic::{composition::Composition, game::Classic, play::Trigger, roles}, roles::Faction, scenario::*, waiting::{WaitingBuilder, WaitingRoom}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bots {
+    Random,
+    Heuristic,
+    Mixed,
+}
+
+impl Bots {
+    fn make(&self, seat: usize, seed: u64) -> Box<dyn Bot> {
+        match self {
+            Bots::Random => Box::new(RandomBot::new(seed)),
+            Bots::Heuristic => Box::new(HeuristicBot::new(seed)),
+            Bots::Mixed if seat.is_multiple_of(2) => Box::new(RandomBot::new(seed)),
+            Bots::Mixed => Box::new(HeuristicBot::new(seed)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub games: usize,
+    pub mafia_won: usize,
+    pub city_won: usize,
+    pub wins: BTreeMap<Faction, usize>,
+    pub stalled: usize,
+    pub days: usize,
+    pub holders: BTreeMap<Power, usize>,
+    pub survivors: BTreeMap<Power, usize>,
+    pub triggers: BTreeMap<Trigger, usize>,
+}
+
+impl Report {
+    fn ratio(n: usize, of: usize) -> f64 {
+        if of == 0 {
+            0.0
+        } else {
+            n as f64 / of as f64
+        }
+    }
+
+    pub fn mafia_rate(&self) -> f64 {
+        Self::ratio(self.mafia_won, self.games)
+    }
+
+    pub fn city_rate(&self) -> f64 {
+        Self::ratio(self.city_won, self.games)
+    }
+
+    pub fn average_days(&self) -> f64 {
+        Self::ratio(self.days, self.games)
+    }
+
+    pub fn survival(&self, power: &Power) -> f64 {
+        let holders = self.holders.get(power).cloned().unwrap_or(0);
+        Self::ratio(self.survivors.get(power).cloned().unwrap_or(0), holders)
+    }
+
+    fn record(&mut self, game: &Classic) {
+        self.games += 1;
+        self.days += game.day();
+        match game.state() {
+            CityState::Done(state) => {
+                let winners = state.winners();
+                if winners.contains(&Faction::Mafia) {
+                    self.mafia_won += 1;
+                }
+                if winners.contains(&Faction::City) {
+                    self.city_won += 1;
+                }
+                for faction in winners.iter() {
+                    *self.wins.entry(*faction).or_insert(0) += 1;
+                }
+            },
+            _ => self.stalled += 1,
+        }
+
+        let alive = game.alive();
+        for (id, powers) in game.roles() {
+            for power in powers {
+                *self.holders.entry(*power).or_insert(0) += 1;
+                if alive.contains(id) {
+                    *self.survivors.entry(*power).or_insert(0) += 1;
+                }
+            }
+        }
+
+        for (_, trigger) in game.triggers() {
+            *self.triggers.entry(*trigger).or_insert(0) += 1;
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "games:        {}", self.games)?;
+        writeln!(f, "mafia won:    {} ({:.1}%)", self.mafia_won, self.mafia_rate() * 100.0)?;
+        writeln!(f, "city won:     {} ({:.1}%)", self.city_won, self.city_rate() * 100.0)?;
+        for (faction, n) in self.wins.iter().filter(|(f, _)| !matches!(f, Faction::City | Faction::Mafia)) {
+            writeln!(f, "{:<13} {} ({:.1}%)", format!("{:?} won:", faction), n, Self::ratio(*n, self.games) * 100.0)?;
+        }
+        writeln!(f, "stalled:      {}", self.stalled)?;
+        writeln!(f, "average days: {:.2}", self.average_days())?;
+        writeln!(f, "survival:")?;
+        for power in self.holders.keys() {
+            writeln!(f, "  {:<14} {:.1}%", format!("{:?}", power), self.survival(power) * 100.0)?;
+        }
+        writeln!(f, "triggers:")?;
+        for (trigger, n) in self.triggers.iter() {
+            writeln!(f, "  {:<24} {} ({:.2} per game)", format!("{:?}", trigger), n, Self::ratio(*n, self.games))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Simulation {
+    composition: Composition,
+    bots: Bots,
+    patience: usize,
+    max_steps: usize,
+}
+
+impl Simulation {
+    pub fn new(composition: Composition, bots: Bots) -> Self {
+        Self { composition, bots, patience: 3, max_steps: 1000 }
+    }
+
+    pub fn play(&self, seed: u64) -> Result<Classic, Error> {
+        let mut rng = GameRng::seed_from_u64(seed);
+        let mut waiting = WaitingBuilder::new(self.composition.players());
+        let abilities = roles::registry().abilities().map(|a| a.power).collect::<BTreeSet<_>>();
+        let mut seats = Vec::new();
+        while let Some((id, _, oracle)) = waiting.reserve() {
+            let bot = self.bots.make(seats.len(), rng.gen());
+            seats.push(BotSeat::new(id, oracle, bot, abilities.clone()));
+        }
+
+        let room = waiting.get()
+            .ok_or(Config::Unfilled)?;
+        let mut game = Classic::new(room, self.composition.clone(), rng.gen())?;
+
+        let mut waited = 0;
+        for _ in 0..self.max_steps {
+            for seat in seats.iter_mut() {
+                seat.tick();
+            }
+
+            if game.process() || waited >= self.patience {
+                game.next();
+                waited = 0;
+            } else {
+                waited += 1;
+            }
+
+            if let CityState::Done(_) = game.state() {
+                break
+            }
+        }
+        Ok(game)
+    }
+
+    pub fn run(&self, games: usize, seed: u64) -> Result<Report, Error> {
+        let mut rng = GameRng::seed_from_u64(seed);
+        let mut report = Report::default();
+        for _ in 0..games {
+            let game = self.play(rng.gen())?;
+            report.record(&game);
+        }
+        Ok(report)
+    }
+}
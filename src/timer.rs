@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{clock::Clock, scenario::*};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Timetable {
+    pub debate: Duration,
+    pub defend: Duration,
+    pub hang: Duration,
+    pub night: Duration,
+}
+
+impl Default for Timetable {
+    fn default() -> Self {
+        Self {
+            debate: Duration::from_secs(300),
+            defend: Duration::from_secs(60),
+            hang: Duration::from_secs(30),
+            night: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Timetable {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::Serialization(format!("Invalid timetable: {}", e)))
+    }
+
+    pub fn of(&self, state: CityState) -> Option<Duration> {
+        match state {
+            CityState::Debate => Some(self.debate),
+            CityState::Defend => Some(self.defend),
+            CityState::Hang => Some(self.hang),
+            CityState::Night => Some(self.night),
+            CityState::Done(_) => None,
+        }
+    }
+}
+
+pub struct Timed<S: Scenario, C: Clock> {
+    game: S,
+    clock: C,
+    timetable: Timetable,
+    phase: (Day, CityState),
+    started: Duration,
+}
+
+impl<S: Scenario, C: Clock> Timed<S, C> {
+    pub fn new(game: S, clock: C, timetable: Timetable) -> Self {
+        let phase = (game.day(), game.state());
+        let started = clock.now();
+        Self { game, clock, timetable, phase, started }
+    }
+
+    pub fn game(&self) -> &S {
+        &self.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut S {
+        &mut self.game
+    }
+
+    pub fn into_game(self) -> S {
+        self.game
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        self.timetable.of(self.game.state()).map(|d| self.started + d)
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline().map(|d| d.saturating_sub(self.clock.now()))
+    }
+
+    fn watch(&mut self) {
+        let phase = (self.game.day(), self.game.state());
+        if phase != self.phase {
+            self.phase = phase;
+            self.started = self.clock.now();
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.deadline().is_some_and(|d| self.clock.now() >= d)
+    }
+}
+
+impl<S: Scenario, C: Clock> Scenario for Timed<S, C> {
+    fn state(&self) -> CityState {
+        self.game.state()
+    }
+
+    fn day(&self) -> Day {
+        self.game.day()
+    }
+
+    fn next(&mut self) -> CityState {
+        let state = self.game.next();
+        self.phase = (self.game.day(), state.clone());
+        self.started = self.clock.now();
+        state
+    }
+
+    fn events(&self) -> &Vec<(Day, CityState, Declaration)> {
+        self.game.events()
+    }
+
+    fn process(&mut self) -> bool {
+        self.watch();
+        let ready = self.game.process();
+        if !ready && self.expired() {
+            self.next();
+            return false
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{clock::ManualClock, elections::{tie::TiePolicy, voting::{Threshold, Voting}}, games::classic::{composition::Composition, game::Classic}, waiting::{connect, OracleRef, PlayerId}};
+
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn timed(voting: Voting) -> (Timed<Classic, ManualClock>, ManualClock, BTreeMap<PlayerId, OracleRef>) {
+        let (mut gods, mut oracles) = (BTreeMap::new(), BTreeMap::new());
+        for id in 1..=10 {
+            let (god, oracle) = connect();
+            gods.insert(id, god);
+            oracles.insert(id, oracle);
+        }
+        let game = Classic::new(gods, Composition::recommended(10).unwrap().with_voting(voting), 11).unwrap();
+        let clock = ManualClock::new();
+        (Timed::new(game, clock.clone(), Timetable::default()), clock, oracles)
+    }
+
+    fn send(oracles: &mut BTreeMap<PlayerId, OracleRef>, action: Act, votes: &[(PlayerId, PlayerId)]) {
+        for (from, on) in votes {
+            oracles.get_mut(from).unwrap().tell(Pray { action, query: vec![*on], meta: None });
+        }
+    }
+
+    /// Lets `phase` run out: nothing happens a second early, the game moves on at the deadline.
+    fn expire(game: &mut Timed<Classic, ManualClock>, clock: &ManualClock, phase: Duration) {
+        let state = game.state();
+        clock.advance(phase - SECOND);
+        game.process();
+        assert_eq!(game.state(), state);
+        clock.advance(SECOND);
+        game.process();
+    }
+
+    fn verdict(game: &Timed<Classic, ManualClock>) -> Option<PlayerId> {
+        game.events().iter().rev().find_map(|(_, _, d)| match d {
+            Declaration::Verdict(verdict) => Some(verdict.hanged),
+            _ => None,
+        }).flatten()
+    }
+
+    #[test]
+    fn each_phase_closes_at_its_deadline() {
+        let (mut game, clock, mut oracles) = timed(Voting::default());
+        let table = Timetable::default();
+        send(&mut oracles, Act::Nominate, &[(2, 1), (3, 1), (4, 1), (5, 1), (6, 1)]);
+
+        expire(&mut game, &clock, table.debate);
+        assert_eq!(game.state(), CityState::Defend);
+        expire(&mut game, &clock, table.defend);
+        assert_eq!(game.state(), CityState::Hang);
+        expire(&mut game, &clock, table.hang);
+        assert_eq!(game.state(), CityState::Night);
+        expire(&mut game, &clock, table.night);
+        assert_eq!((game.state(), game.day()), (CityState::Debate, 1));
+    }
+
+    #[test]
+    fn silent_players_cast_no_vote_and_no_night_action() {
+        let (mut game, clock, mut oracles) = timed(Voting::default());
+        let table = Timetable::default();
+        send(&mut oracles, Act::Nominate, &[(2, 1), (3, 1), (4, 1), (5, 1), (6, 1)]);
+        expire(&mut game, &clock, table.debate);
+        expire(&mut game, &clock, table.defend);
+
+        send(&mut oracles, Act::Vote, &[(2, 1), (3, 1)]);
+        expire(&mut game, &clock, table.hang);
+        assert_eq!(verdict(&game), None);
+        assert_eq!(game.game().alive().len(), 10);
+
+        expire(&mut game, &clock, table.night);
+        assert_eq!(game.game().alive().len(), 10);
+    }
+
+    #[test]
+    fn a_revote_gets_a_fresh_deadline() {
+        let voting = Voting { tie: TiePolicy::Revote, nominate: Threshold::Plurality, hang: Threshold::Plurality };
+        let (mut game, clock, mut oracles) = timed(voting);
+        let table = Timetable::default();
+        send(&mut oracles, Act::Nominate, &[(3, 1), (4, 1), (5, 2), (6, 2)]);
+        expire(&mut game, &clock, table.debate);
+        expire(&mut game, &clock, table.defend);
+
+        send(&mut oracles, Act::Vote, &[(3, 1), (4, 2)]);
+        clock.advance(table.hang);
+        game.process();
+        assert_eq!(game.state(), CityState::Hang);
+        assert_eq!(game.remaining(), Some(table.hang));
+
+        send(&mut oracles, Act::Vote, &[(3, 1), (4, 1), (5, 2)]);
+        expire(&mut game, &clock, table.hang);
+        assert_eq!(verdict(&game), Some(1));
+        assert_eq!(game.state(), CityState::Night);
+    }
+}
@@ -1,7 +1,10 @@
 use std::{collections::{BTreeMap, BTreeSet}, iter::FromIterator};
 
+use nanoid::nanoid;
+
 
 pub type PlayerId = u32;
+pub type Token = String;
 
 // impl Ord for Player {
 //     fn cmp(&self, other: &Self) -> Ordering {
@@ -23,16 +26,21 @@ pub type PlayerId = u32;
 
 // impl Eq for Player {}
 
-use crate::{oracle::{Oracle, TwoWayRing}, scenario::{HolyMessage, Power, Pray}};
+use crate::{oracle::{Oracle, TwoWayRing}, scenario::{HolyMessage, Pray}};
 
 pub type OracleRef = TwoWayRing<Pray, HolyMessage>;
 pub type GodWayRef = TwoWayRing<HolyMessage, Pray>;
 
+pub fn connect() -> (GodWayRef, OracleRef) {
+    Oracle::create(64, 8)
+}
+
 pub trait WaitingRoom {
     type Oracle;
     type God;
-    fn reserve(&mut self) -> Option<(PlayerId, Self::Oracle)>;
+    fn reserve(&mut self) -> Option<(PlayerId, Token, Self::Oracle)>;
     fn opt_out(&mut self, id: &PlayerId) -> bool;
+    fn tokens(&self) -> BTreeMap<Token, PlayerId>;
     fn limit(&self) -> usize;
     fn ready(&self) -> bool;
     fn get(self) -> Option<BTreeMap<PlayerId, Self::God>>;
@@ -41,24 +49,26 @@ pub trait WaitingRoom {
 pub struct WaitingBuilder {
     limit: usize,
     waiting: BTreeMap<PlayerId, GodWayRef>,
+    tokens: BTreeMap<PlayerId, Token>,
 }
 
 impl WaitingBuilder {
     pub fn new(limit: usize) -> WaitingBuilder {
         WaitingBuilder {
             waiting: BTreeMap::new(),
+            tokens: BTreeMap::new(),
             limit
         }
     }
 
     #[inline]
-    fn total(&self) -> usize {
+    pub fn total(&self) -> usize {
         self.waiting.len()
     }
 
     fn next_id(&self) -> Option<PlayerId> {
-        let range = 1..self.limit as u32;
-        let mut ids: BTreeSet<u32> = BTreeSet::from_iter(range.into_iter());
+        let range = 1..=self.limit as u32;
+        let mut ids: BTreeSet<u32> = BTreeSet::from_iter(range);
         for i in self.waiting.keys() {
             ids.remove(i);
         }
@@ -70,15 +80,19 @@ impl WaitingRoom for WaitingBuilder {
     type Oracle = TwoWayRing<Pray, HolyMessage>;
     type God = TwoWayRing<HolyMessage, Pray>;
 
-    fn reserve(&mut self) -> Option<(PlayerId, Self::Oracle)> {
+    fn reserve(&mut self) -> Option<(PlayerId, Token, Self::Oracle)> {
         let id = self.next_id()?;
         if self.ready() || self.waiting.contains_key(&id) {
             return None
         }
 
-        let (channel, oracle) = Oracle::create(10, 1);
+        let (mut channel, oracle) = connect();
+        let token: Token = nanoid!();
+        channel.tell(HolyMessage::Seated(id));
+        channel.tell(HolyMessage::Session(token.clone()));
         self.waiting.insert(id, channel);
-        Some((id, oracle))
+        self.tokens.insert(id, token.clone());
+        Some((id, token, oracle))
     }
 
     #[inline]
@@ -87,9 +101,14 @@ impl WaitingRoom for WaitingBuilder {
     }
 
     fn opt_out(&mut self, id: &PlayerId) -> bool {
+        self.tokens.remove(id);
         self.waiting.remove(id).is_some()
     }
 
+    fn tokens(&self) -> BTreeMap<Token, PlayerId> {
+        self.tokens.iter().map(|(id, token)| (token.clone(), *id)).collect()
+    }
+
     #[inline]
     fn ready(&self) -> bool {
         self.total() == self.limit()